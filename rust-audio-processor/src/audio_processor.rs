@@ -1,42 +1,74 @@
 // Audio Processing for Computing Spectogram (WASM Interface)
 use crate::utils::Complex;
 use crate::fft::{fft_with_cache, TwiddleCache};
-use crate::hann_window::apply_hann_window;
+use crate::hann_window::generate_hann_window;
+use crate::progress::{report_interval, CancellationToken, ProcessError};
 
 // Only include wasm-bindgen stuff when compiling for WASM target
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 use wasm_bindgen::prelude::*;
 
 // set up panic hook for better WASM debugging (WASM only)
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 #[wasm_bindgen(start)]
 pub fn main() {
     console_error_panic_hook::set_once();
 }
 
 // import console.log for debugging (WASM only) ->
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
+    pub(crate) fn log(s: &str);
 }
 
-// Console logging macro - works in both WASM and rust 
+// Console logging macro - goes to the browser console with the `wasm` bindings,
+// to stdout for the `cli` build, and stays quiet for plain library users
 macro_rules! console_log {
     ($($t:tt)*) => {
-        #[cfg(target_arch = "wasm32")]
-        log(&format_args!($($t)*).to_string());
-        
-        #[cfg(not(target_arch = "wasm32"))]
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+        $crate::audio_processor::log(&format_args!($($t)*).to_string());
+
+        #[cfg(all(feature = "cli", not(all(feature = "wasm", target_arch = "wasm32"))))]
         println!($($t)*);
+
+        #[cfg(not(any(feature = "cli", all(feature = "wasm", target_arch = "wasm32"))))]
+        let _ = format_args!($($t)*);
     };
 }
 
+// Window + FFT one frame into `buffer`. Only reads the shared tables, so parallel
+// workers can call it with their own scratch buffer.
+pub(crate) fn transform_frame(buffer: &mut [Complex], audio_data: &[f32], window: &[f32], twiddle_cache: &TwiddleCache) {
+    // Copy windowed samples into the complex buffer
+    for ((slot, &sample), &w) in buffer.iter_mut().zip(audio_data.iter()).zip(window.iter()) {
+        slot.real = sample * w;
+        slot.imag = 0.0;
+    }
+
+    // Perform FFT using cached twiddles
+    fft_with_cache(buffer, twiddle_cache);
+}
+
+// Hop between consecutive `frame_len` frames for an overlap fraction (never 0)
+pub(crate) fn hop_size(frame_len: usize, overlap: f32) -> usize {
+    (((frame_len as f32) * (1.0 - overlap)) as usize).max(1)
+}
+
+// First sample of every `frame_len` frame that fits in `len` samples, `hop_size` apart.
+// The one frame walk behind the STFT views and the frame-based analysers.
+pub(crate) fn frame_starts(len: usize, frame_len: usize, hop_size: usize) -> impl ExactSizeIterator<Item = usize> {
+    let num_frames = if len >= frame_len { (len - frame_len) / hop_size + 1 } else { 0 };
+    (0..num_frames).map(move |frame_idx| frame_idx * hop_size)
+}
+
 // Core FFT processor that works in both WASM and native environments
 pub struct SpectrogramProcessor {
     fft_size: usize,
     twiddle_cache: TwiddleCache,
+    // Hann coefficients, computed once per processor
+    window: Vec<f32>,
     buffer: Vec<Complex>,
     time_stride: usize,
     freq_stride: usize,
@@ -48,10 +80,12 @@ impl SpectrogramProcessor {
         console_log!("Creating SpectrogramProcessor with FFT size: {}", fft_size);
         assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
         let twiddle_cache = TwiddleCache::new(fft_size);
+        let window = generate_hann_window(fft_size);
         let buffer = vec![Complex::new(0.0, 0.0); fft_size];
         SpectrogramProcessor {
             fft_size,
             twiddle_cache,
+            window,
             buffer,
             time_stride: 1,
             freq_stride: 1,
@@ -81,24 +115,49 @@ impl SpectrogramProcessor {
         self.freq_stride
     }
 
-    // Process a single audio window and return magnitudes
-    pub fn process_window(&mut self, audio_data: &[f32]) -> Vec<f32> {
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    pub fn window(&self) -> &[f32] {
+        &self.window
+    }
+
+    pub fn twiddle_cache(&self) -> &TwiddleCache {
+        &self.twiddle_cache
+    }
+
+    // Hop between consecutive windows for a given overlap fraction (never 0)
+    pub fn hop_size(&self, overlap: f32) -> usize {
+        hop_size(self.fft_size, overlap)
+    }
+
+    // First sample of every fft_size frame in `len` input samples, strides ignored
+    pub(crate) fn frame_starts(&self, len: usize, overlap: f32) -> impl ExactSizeIterator<Item = usize> {
+        frame_starts(len, self.fft_size, self.hop_size(overlap))
+    }
+
+    // `frame_starts` thinned by the time stride: the windows `process_windows` emits
+    pub(crate) fn window_starts(&self, len: usize, overlap: f32) -> impl ExactSizeIterator<Item = usize> {
+        self.frame_starts(len, overlap).step_by(self.time_stride)
+    }
+
+    // Window + FFT one frame into the reusable buffer; false if the length is wrong
+    fn transform_window(&mut self, audio_data: &[f32]) -> bool {
         if audio_data.len() != self.fft_size {
             console_log!("⚠️ Audio data length {} != fft_size {}", audio_data.len(), self.fft_size);
-            return Vec::new();
-        }
-
-        // Copy samples into reusable complex buffer
-        for (slot, &sample) in self.buffer.iter_mut().zip(audio_data.iter()) {
-            slot.real = sample;
-            slot.imag = 0.0;
+            return false;
         }
 
-        // Apply Hann window
-        apply_hann_window(&mut self.buffer);
+        transform_frame(&mut self.buffer, audio_data, &self.window, &self.twiddle_cache);
+        true
+    }
 
-        // Perform FFT using cached twiddles
-        fft_with_cache(&mut self.buffer, &self.twiddle_cache);
+    // Process a single audio window and return magnitudes
+    pub fn process_window(&mut self, audio_data: &[f32]) -> Vec<f32> {
+        if !self.transform_window(audio_data) {
+            return Vec::new();
+        }
 
         // Calculate magnitudes (first half due to symmetry)
         let magnitudes: Vec<f32> = self.buffer[0..self.fft_size / 2]
@@ -109,81 +168,176 @@ impl SpectrogramProcessor {
         magnitudes
     }
 
+    // Process a single audio window and return the complex spectrum.
+    // Unlike the magnitude path this keeps the Nyquist bin (fft_size / 2 + 1 values)
+    // so the frame can be inverted exactly by `Istft`.
+    pub fn process_window_complex(&mut self, audio_data: &[f32]) -> Vec<Complex> {
+        if !self.transform_window(audio_data) {
+            return Vec::new();
+        }
+
+        self.buffer[0..=self.fft_size / 2].to_vec()
+    }
+
     // Process complete spectrogram from audio data
     pub fn compute_spectrogram(&mut self, audio_data: &[f32], overlap: f32) -> Vec<f32> {
-        
+        // Keep the old ~10% console logging on top of the shared progress path
+        let mut logged_decile = 0;
+        let log_progress = |done: usize, total: usize| {
+            let decile = done * 10 / total;
+            if total > 100 && decile > logged_decile {
+                logged_decile = decile;
+                console_log!("Progress: {}/{} windows", done, total);
+            }
+        };
+        match self.compute_spectrogram_with_progress(audio_data, overlap, log_progress, None) {
+            Ok(spectrogram) => spectrogram,
+            Err(ProcessError::Cancelled { .. }) => unreachable!("no cancellation token was given"),
+        }
+    }
+
+    // `compute_spectrogram` with the same progress callback and cancellation token as
+    // `process_windows_with_progress`. Strides don't apply here, as before.
+    pub fn compute_spectrogram_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        audio_data: &[f32],
+        overlap: f32,
+        mut progress: F,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<f32>, ProcessError> {
         console_log!("Starting spectrogram computation for {} samples", audio_data.len());
 
-        let hop_size = ((self.fft_size as f32) * (1.0 - overlap)) as usize;
-        let num_windows = if audio_data.len() >= self.fft_size {
-            (audio_data.len() - self.fft_size) / hop_size + 1
-        } else {
-            0
-        };
-        
-        console_log!("Processing {} windows with hop size {}", num_windows, hop_size);
-        
-        let mut spectrogram_flat = Vec::new();
-        
-        for window_idx in 0..num_windows {
-            let start_idx = window_idx * hop_size;
-            let end_idx = start_idx + self.fft_size;
-
-            if end_idx <= audio_data.len() {
-                let window_slice = &audio_data[start_idx..end_idx];
-                let magnitudes = self.process_window(window_slice);
-                spectrogram_flat.extend(magnitudes);
+        let freq_bins = self.fft_size / 2;
+        let starts = self.frame_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+
+        console_log!("Processing {} windows with hop size {}", num_windows, self.hop_size(overlap));
+
+        let mut spectrogram_flat = Vec::with_capacity(num_windows * freq_bins);
+        let interval = report_interval(num_windows);
+
+        for (window_idx, start_idx) in starts.enumerate() {
+            if cancel.is_some_and(|token| token.is_cancelled()) {
+                console_log!("compute_spectrogram cancelled at {}/{} windows", window_idx, num_windows);
+                return Err(ProcessError::Cancelled {
+                    data: spectrogram_flat,
+                    num_windows: window_idx,
+                    freq_bins,
+                    total_windows: num_windows,
+                });
             }
-            
-            // Progress logging
-            if num_windows > 100 && window_idx % (num_windows / 10) == 0 {
-                console_log!("Progress: {}/{} windows", window_idx, num_windows);
+
+            let window_slice = &audio_data[start_idx..start_idx + self.fft_size];
+            spectrogram_flat.extend(self.process_window(window_slice));
+
+            if (window_idx + 1) % interval == 0 || window_idx + 1 == num_windows {
+                progress(window_idx + 1, num_windows);
             }
         }
-        
-        console_log!("Spectrogram generation complete: {} x {}", num_windows, self.fft_size / 2);
-        spectrogram_flat
+
+        console_log!("Spectrogram generation complete: {} x {}", num_windows, freq_bins);
+        Ok(spectrogram_flat)
     }
 
     pub fn process_windows(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, usize, usize) {
-        let hop_size = ((self.fft_size as f32) * (1.0 - overlap)) as usize;
+        match self.process_windows_with_progress(audio_data, overlap, |_, _| {}, None) {
+            Ok(batch) => batch,
+            Err(ProcessError::Cancelled { .. }) => unreachable!("no cancellation token was given"),
+        }
+    }
+
+    // `process_windows` with a progress callback (windows done, total) fired in ~1% steps
+    // and on the last window, and a cancellation token checked before every frame.
+    // Cancelling yields `ProcessError::Cancelled` holding the windows finished so far.
+    pub fn process_windows_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        audio_data: &[f32],
+        overlap: f32,
+        mut progress: F,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(Vec<f32>, usize, usize), ProcessError> {
         let freq_bins = self.fft_size / 2;
 
         if audio_data.len() < self.fft_size {
-            return (Vec::new(), 0, freq_bins);
+            return Ok((Vec::new(), 0, freq_bins));
         }
 
-        let total_windows = (audio_data.len() - self.fft_size) / hop_size + 1;
-        let num_windows = (0..total_windows).step_by(self.time_stride).count();
-        let reduced_bins = (freq_bins + self.freq_stride - 1) / self.freq_stride;
+        let starts = self.window_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+        let reduced_bins = freq_bins.div_ceil(self.freq_stride);
         console_log!(
             "process_windows batching {} logical windows (stride {})",
             num_windows,
             self.time_stride
         );
         let mut result = Vec::with_capacity(num_windows * reduced_bins);
+        let interval = report_interval(num_windows);
+
+        for (done, start_idx) in starts.enumerate() {
+            if cancel.is_some_and(|token| token.is_cancelled()) {
+                console_log!("process_windows cancelled at {}/{} windows", done, num_windows);
+                return Err(ProcessError::Cancelled {
+                    data: result,
+                    num_windows: done,
+                    freq_bins: reduced_bins,
+                    total_windows: num_windows,
+                });
+            }
 
-        for window_idx in (0..total_windows).step_by(self.time_stride) {
-            let start_idx = window_idx * hop_size;
-            let end_idx = start_idx + self.fft_size;
-            let window_slice = &audio_data[start_idx..end_idx];
+            let window_slice = &audio_data[start_idx..start_idx + self.fft_size];
             let magnitudes = self.process_window(window_slice);
             result.extend(magnitudes.into_iter().step_by(self.freq_stride));
+
+            if (done + 1) % interval == 0 || done + 1 == num_windows {
+                progress(done + 1, num_windows);
+            }
         }
 
-        (result, num_windows, reduced_bins)
+        Ok((result, num_windows, reduced_bins))
+    }
+
+    // Full complex STFT: fft_size / 2 + 1 bins per window, flattened window-major.
+    // Strides are a display decimation and are ignored here so the result stays invertible.
+    pub fn process_windows_complex(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<Complex>, usize, usize) {
+        let freq_bins = self.fft_size / 2 + 1;
+        let starts = self.frame_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+        let mut result = Vec::with_capacity(num_windows * freq_bins);
+
+        for start_idx in starts {
+            let window_slice = &audio_data[start_idx..start_idx + self.fft_size];
+            result.extend(self.process_window_complex(window_slice));
+        }
+
+        (result, num_windows, freq_bins)
+    }
+
+    // Magnitude + phase split of `process_windows_complex`
+    pub fn process_windows_polar(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, Vec<f32>, usize, usize) {
+        let (spectrum, num_windows, freq_bins) = self.process_windows_complex(audio_data, overlap);
+        let magnitudes = spectrum.iter().map(|c| c.magnitude()).collect();
+        let phases = spectrum.iter().map(|c| c.phase()).collect();
+        (magnitudes, phases, num_windows, freq_bins)
     }
 }
 
 // WASM-specific exports (only compiled for WASM target)
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 mod wasm_exports {
     use super::*;
-    use wasm_bindgen::prelude::*;
+    use crate::cqt::ConstantQ;
+    use crate::istft::Istft;
+    use crate::log_frequency::{LogFrequencyMapper, RowAggregation};
+    use crate::multichannel::ChannelLayout;
+    use crate::multires::MultiResolutionStft;
+    use crate::reassignment::ReassignmentProcessor;
+    use crate::zoom::ZoomFft;
 
     #[wasm_bindgen]
     pub struct SpectrogramBatch {
         data: Vec<f32>,
+        // stacked planes, each num_windows x freq_bins (1 for single-channel calls)
+        num_channels: u32,
         num_windows: u32,
         freq_bins: u32,
     }
@@ -195,6 +349,11 @@ mod wasm_exports {
             self.data.clone()
         }
 
+        #[wasm_bindgen(getter)]
+        pub fn num_channels(&self) -> u32 {
+            self.num_channels
+        }
+
         #[wasm_bindgen(getter)]
         pub fn num_windows(&self) -> u32 {
             self.num_windows
@@ -247,10 +406,350 @@ mod wasm_exports {
             let (data, num_windows, freq_bins) = self.inner.process_windows(audio_data, overlap);
             SpectrogramBatch {
                 data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // `on_progress(done, total)` is called in ~1% steps; returning `false` from it stops
+        // the run, which then rejects with a "cancelled after N of M windows" error
+        #[wasm_bindgen]
+        pub fn process_windows_with_progress(
+            &mut self,
+            audio_data: &[f32],
+            overlap: f32,
+            on_progress: &js_sys::Function,
+            time_stride: Option<usize>,
+            freq_stride: Option<usize>,
+        ) -> Result<SpectrogramBatch, JsValue> {
+            let new_time = time_stride.unwrap_or(self.inner.time_stride());
+            let new_freq = freq_stride.unwrap_or(self.inner.freq_stride());
+            self.inner.set_strides(new_time, new_freq);
+
+            let token = CancellationToken::new();
+            let callback = |done: usize, total: usize| {
+                let keep_going = on_progress
+                    .call2(&JsValue::NULL, &JsValue::from(done as u32), &JsValue::from(total as u32))
+                    .map(|ret| ret.as_bool() != Some(false))
+                    .unwrap_or(false);
+                if !keep_going {
+                    token.cancel();
+                }
+            };
+
+            let (data, num_windows, freq_bins) = self
+                .inner
+                .process_windows_with_progress(audio_data, overlap, callback, Some(&token))
+                .map_err(|e| JsValue::from(JsError::new(&e.to_string())))?;
+            Ok(SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            })
+        }
+
+        // Complex STFT with interleaved [re, im] pairs; freq_bins is fft_size / 2 + 1
+        #[wasm_bindgen]
+        pub fn process_windows_complex(&mut self, audio_data: &[f32], overlap: f32) -> SpectrogramBatch {
+            let (spectrum, num_windows, freq_bins) = self.inner.process_windows_complex(audio_data, overlap);
+            SpectrogramBatch {
+                data: interleave_complex(&spectrum),
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // Planes come back as: each input channel, the downmix, then mid and side for stereo
+        #[wasm_bindgen]
+        pub fn process_windows_multichannel(
+            &mut self,
+            audio_data: &[f32],
+            num_channels: usize,
+            interleaved: bool,
+            overlap: f32,
+        ) -> SpectrogramBatch {
+            let layout = if interleaved {
+                ChannelLayout::Interleaved
+            } else {
+                ChannelLayout::Planar
+            };
+            let result = self.inner.process_windows_multichannel(audio_data, num_channels, layout, overlap);
+            let planes = result.planes();
+            SpectrogramBatch {
+                num_channels: planes.len() as u32,
+                data: planes.concat(),
+                num_windows: result.num_windows as u32,
+                freq_bins: result.freq_bins as u32,
+            }
+        }
+
+        // Per-window descriptors, NUM_FEATURES values each in `SpectralFeatures` field order
+        #[wasm_bindgen]
+        pub fn spectral_features(&mut self, audio_data: &[f32], overlap: f32, sample_rate: f32) -> SpectrogramBatch {
+            let (_, num_windows, _, features) = self.inner.process_windows_with_features(audio_data, overlap, sample_rate);
+            SpectrogramBatch {
+                data: features.iter().flat_map(|f| f.to_array()).collect(),
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: crate::features::NUM_FEATURES as u32,
+            }
+        }
+
+        // Log-frequency view: the mapper's rows (bottom first) instead of linear bins
+        #[wasm_bindgen]
+        pub fn process_windows_log(&mut self, audio_data: &[f32], overlap: f32, mapper: &WasmLogFrequencyMapper) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.process_windows_log(audio_data, overlap, &mapper.inner);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // Band-limited frames from a zoom plan built for this fft_size
+        #[wasm_bindgen]
+        pub fn process_windows_zoom(&mut self, audio_data: &[f32], overlap: f32, zoom: &WasmZoomFft) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.process_windows_zoom(audio_data, overlap, &zoom.inner);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // Average-panel curves, one row each: Welch PSD, LTAS, max hold, min hold and the
+        // 10th, 50th and 90th percentile spectra; freq_bins is fft_size / 2
+        #[wasm_bindgen]
+        pub fn long_term_spectrum(&mut self, audio_data: &[f32], overlap: f32, sample_rate: f32) -> SpectrogramBatch {
+            let spectrum = self.inner.long_term_spectrum(audio_data, overlap, sample_rate, &[0.1, 0.5, 0.9]);
+            let mut rows = vec![spectrum.psd_db_per_hz, spectrum.ltas_db, spectrum.max_hold_db, spectrum.min_hold_db];
+            rows.extend(spectrum.percentiles.into_iter().map(|p| p.levels_db));
+            SpectrogramBatch {
+                num_windows: rows.len() as u32,
+                data: rows.concat(),
+                num_channels: 1,
+                freq_bins: (self.inner.fft_size() / 2) as u32,
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn hop_size(&self, overlap: f32) -> usize {
+            self.inner.hop_size(overlap)
+        }
+    }
+
+    // Built once per view setting and reused for every batch. `max_aggregation` keeps
+    // narrow tones bright in the dense top octaves; otherwise rows take the power mean.
+    #[wasm_bindgen]
+    pub struct WasmLogFrequencyMapper {
+        inner: LogFrequencyMapper,
+    }
+
+    #[wasm_bindgen]
+    impl WasmLogFrequencyMapper {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32, fft_size: usize, min_hz: f32, max_hz: f32, rows: usize, max_aggregation: bool) -> WasmLogFrequencyMapper {
+            let aggregation = if max_aggregation {
+                RowAggregation::Max
+            } else {
+                RowAggregation::Mean
+            };
+            WasmLogFrequencyMapper {
+                inner: LogFrequencyMapper::new(sample_rate, fft_size, min_hz, max_hz, rows).with_aggregation(aggregation),
+            }
+        }
+
+        // Centre frequency of every row, for axis labels
+        #[wasm_bindgen]
+        pub fn row_frequencies(&self) -> Vec<f32> {
+            self.inner.row_frequencies().to_vec()
+        }
+    }
+
+    // Chirp-z plan for one band, e.g. 15-22 kHz to inspect encoder lowpasses
+    #[wasm_bindgen]
+    pub struct WasmZoomFft {
+        inner: ZoomFft,
+    }
+
+    #[wasm_bindgen]
+    impl WasmZoomFft {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32, fft_size: usize, min_hz: f32, max_hz: f32, bins: usize) -> WasmZoomFft {
+            WasmZoomFft {
+                inner: ZoomFft::new(sample_rate, fft_size, min_hz, max_hz, bins),
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn bin_frequencies(&self) -> Vec<f32> {
+            self.inner.bin_frequencies().to_vec()
+        }
+    }
+
+    // Constant-Q spectrogram in the same batch layout as `process_windows`, so it goes
+    // through the same colormap and render path
+    #[wasm_bindgen]
+    pub struct WasmConstantQ {
+        inner: ConstantQ,
+    }
+
+    #[wasm_bindgen]
+    impl WasmConstantQ {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32, min_hz: f32, max_hz: f32, bins_per_octave: usize, hop_size: usize) -> WasmConstantQ {
+            WasmConstantQ {
+                inner: ConstantQ::new(sample_rate, min_hz, max_hz, bins_per_octave).with_hop_size(hop_size),
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn process(&self, audio_data: &[f32]) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.process(audio_data);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // The hop actually used (rounded up to a multiple of 2^(octaves - 1))
+        #[wasm_bindgen]
+        pub fn hop_size(&self) -> usize {
+            self.inner.hop_size()
+        }
+
+        #[wasm_bindgen]
+        pub fn bin_frequencies(&self) -> Vec<f32> {
+            self.inner.bin_frequencies().to_vec()
+        }
+    }
+
+    // Multi-resolution spectrogram, also in the `process_windows` batch layout. Sizes run
+    // longest first with one crossover (Hz) between each pair.
+    #[wasm_bindgen]
+    pub struct WasmMultiResolutionStft {
+        inner: MultiResolutionStft,
+    }
+
+    #[wasm_bindgen]
+    impl WasmMultiResolutionStft {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32, fft_sizes: Vec<u32>, crossovers_hz: Vec<f32>, crossfade_octaves: f32) -> WasmMultiResolutionStft {
+            let sizes: Vec<usize> = fft_sizes.iter().map(|&size| size as usize).collect();
+            WasmMultiResolutionStft {
+                inner: MultiResolutionStft::new(sample_rate, &sizes, &crossovers_hz).with_crossfade(crossfade_octaves),
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn process_windows(&mut self, audio_data: &[f32], overlap: f32) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.process_windows(audio_data, overlap);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn hop_size(&self, overlap: f32) -> usize {
+            self.inner.hop_size(overlap)
+        }
+    }
+
+    #[wasm_bindgen]
+    pub struct WasmReassignmentProcessor {
+        inner: ReassignmentProcessor,
+    }
+
+    #[wasm_bindgen]
+    impl WasmReassignmentProcessor {
+        #[wasm_bindgen(constructor)]
+        pub fn new(sample_rate: f32, fft_size: usize, threshold_db: f32) -> WasmReassignmentProcessor {
+            WasmReassignmentProcessor {
+                inner: ReassignmentProcessor::new(sample_rate, fft_size).with_threshold_db(threshold_db),
+            }
+        }
+
+        #[wasm_bindgen]
+        pub fn reassigned(&mut self, audio_data: &[f32], overlap: f32) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.reassigned(audio_data, overlap);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
                 num_windows: num_windows as u32,
                 freq_bins: freq_bins as u32,
             }
         }
+
+        #[wasm_bindgen]
+        pub fn synchrosqueezed(&mut self, audio_data: &[f32], overlap: f32) -> SpectrogramBatch {
+            let (data, num_windows, freq_bins) = self.inner.synchrosqueezed(audio_data, overlap);
+            SpectrogramBatch {
+                data,
+                num_channels: 1,
+                num_windows: num_windows as u32,
+                freq_bins: freq_bins as u32,
+            }
+        }
+
+        // Three stacked planes: magnitude, instantaneous frequency (Hz), group delay (s)
+        #[wasm_bindgen]
+        pub fn analyze(&mut self, audio_data: &[f32], overlap: f32) -> SpectrogramBatch {
+            let analysis = self.inner.analyze(audio_data, overlap);
+            let mut data = analysis.magnitudes;
+            data.extend(analysis.instantaneous_frequency_hz);
+            data.extend(analysis.group_delay_s);
+            SpectrogramBatch {
+                data,
+                num_channels: 3,
+                num_windows: analysis.num_windows as u32,
+                freq_bins: analysis.freq_bins as u32,
+            }
+        }
+    }
+
+    fn interleave_complex(spectrum: &[Complex]) -> Vec<f32> {
+        spectrum.iter().flat_map(|c| [c.real, c.imag]).collect()
+    }
+
+    // WASM-exported inverse STFT, takes the interleaved layout from process_windows_complex
+    #[wasm_bindgen]
+    pub struct WasmIstft {
+        inner: Istft,
+    }
+
+    #[wasm_bindgen]
+    impl WasmIstft {
+        #[wasm_bindgen(constructor)]
+        pub fn new(fft_size: usize, hop_size: usize) -> Result<WasmIstft, JsValue> {
+            let inner = Istft::try_new(fft_size, hop_size).map_err(|e| JsValue::from(JsError::new(&e.to_string())))?;
+            Ok(WasmIstft { inner })
+        }
+
+        #[wasm_bindgen]
+        pub fn is_cola(&self, tolerance: f32) -> bool {
+            self.inner.is_cola(tolerance)
+        }
+
+        #[wasm_bindgen]
+        pub fn process(&mut self, interleaved: &[f32], num_windows: usize) -> Result<Vec<f32>, JsValue> {
+            let spectrum: Vec<Complex> = interleaved
+                .chunks_exact(2)
+                .map(|pair| Complex::new(pair[0], pair[1]))
+                .collect();
+            self.inner
+                .try_process(&spectrum, num_windows)
+                .map_err(|e| JsValue::from(JsError::new(&e.to_string())))
+        }
     }
 
     // Simple test functions for WASM integration
@@ -268,7 +767,7 @@ mod wasm_exports {
 }
 
 // Re-export WASM functions only when compiling for WASM
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 pub use wasm_exports::*;
 
 // Tests work on both WASM and native targets
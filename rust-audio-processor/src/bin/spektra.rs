@@ -0,0 +1,38 @@
+// Native front end: spektra <samples.f32> <sample_rate> [fft_size] [overlap]
+// Input is raw mono 32-bit float little-endian PCM (e.g. `ffmpeg -f f32le -ac 1`).
+use rust_audio_processor::{SpectrogramProcessor, TranscodeDetector};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let (Some(path), Some(sample_rate)) = (args.get(1), args.get(2).and_then(|s| s.parse::<f32>().ok())) else {
+        eprintln!("usage: spektra <samples.f32> <sample_rate> [fft_size] [overlap]");
+        return ExitCode::FAILURE;
+    };
+    let fft_size = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(2048usize);
+    let overlap = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(0.5f32);
+    if !fft_size.is_power_of_two() {
+        eprintln!("fft_size must be a power of 2, got {}", fft_size);
+        return ExitCode::FAILURE;
+    }
+
+    let samples: Vec<f32> = match std::fs::read(path) {
+        Ok(bytes) => bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect(),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    let (data, num_windows, freq_bins) = SpectrogramProcessor::new(fft_size).process_windows_parallel(&samples, overlap);
+    #[cfg(not(feature = "rayon"))]
+    let (data, num_windows, freq_bins) = SpectrogramProcessor::new(fft_size).process_windows(&samples, overlap);
+
+    let transcode = TranscodeDetector::new(sample_rate, fft_size).analyze_spectrogram(&data, freq_bins);
+
+    println!("spectrogram: {} windows x {} bins", num_windows, freq_bins);
+    println!("resolution:  {:.2} Hz per bin", sample_rate / fft_size as f32);
+    println!("verdict:     {} (confidence {:.2})", transcode.summary, transcode.confidence);
+    ExitCode::SUCCESS
+}
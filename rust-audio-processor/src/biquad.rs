@@ -0,0 +1,448 @@
+// Second-order IIR sections (direct form I, a0 normalised to 1) and cascades of them
+// Coefficients are f64 so low corner frequencies stay accurate at high sample rates.
+// Single-section designs follow the RBJ Audio EQ Cookbook; higher orders go through
+// analogue zeros/poles and the bilinear transform.
+use std::f64::consts::PI;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    pub fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Biquad {
+        Biquad {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    pub fn lowpass(sample_rate: f64, freq_hz: f64, q: f64) -> Biquad {
+        let (cos_w, alpha) = cookbook_terms(sample_rate, freq_hz, q);
+        Biquad::from_cookbook(
+            [(1.0 - cos_w) / 2.0, 1.0 - cos_w, (1.0 - cos_w) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w, 1.0 - alpha],
+        )
+    }
+
+    pub fn highpass(sample_rate: f64, freq_hz: f64, q: f64) -> Biquad {
+        let (cos_w, alpha) = cookbook_terms(sample_rate, freq_hz, q);
+        Biquad::from_cookbook(
+            [(1.0 + cos_w) / 2.0, -(1.0 + cos_w), (1.0 + cos_w) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w, 1.0 - alpha],
+        )
+    }
+
+    // Bell of `gain_db` at `freq_hz`
+    pub fn peaking(sample_rate: f64, freq_hz: f64, q: f64, gain_db: f64) -> Biquad {
+        let (cos_w, alpha) = cookbook_terms(sample_rate, freq_hz, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        Biquad::from_cookbook(
+            [1.0 + alpha * a, -2.0 * cos_w, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos_w, 1.0 - alpha / a],
+        )
+    }
+
+    // `gain_db` below `freq_hz` (the half-gain point), 0 dB above
+    pub fn low_shelf(sample_rate: f64, freq_hz: f64, q: f64, gain_db: f64) -> Biquad {
+        let (cos_w, alpha) = cookbook_terms(sample_rate, freq_hz, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+        Biquad::from_cookbook(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos_w + root),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w),
+                a * ((a + 1.0) - (a - 1.0) * cos_w - root),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos_w + root,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos_w),
+                (a + 1.0) + (a - 1.0) * cos_w - root,
+            ],
+        )
+    }
+
+    // 0 dB below `freq_hz`, `gain_db` above
+    pub fn high_shelf(sample_rate: f64, freq_hz: f64, q: f64, gain_db: f64) -> Biquad {
+        let (cos_w, alpha) = cookbook_terms(sample_rate, freq_hz, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+        Biquad::from_cookbook(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos_w + root),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w),
+                a * ((a + 1.0) + (a - 1.0) * cos_w - root),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos_w + root,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos_w),
+                (a + 1.0) - (a - 1.0) * cos_w - root,
+            ],
+        )
+    }
+
+    fn from_cookbook(b: [f64; 3], a: [f64; 3]) -> Biquad {
+        Biquad::new(b[0] / a[0], b[1] / a[0], b[2] / a[0], a[1] / a[0], a[2] / a[0])
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+
+    // |H| at `freq_hz`, evaluated on the unit circle
+    pub fn magnitude_at(&self, freq_hz: f64, sample_rate: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * freq_hz / sample_rate;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let num = ((self.b0 + self.b1 * c1 + self.b2 * c2).powi(2) + (self.b1 * s1 + self.b2 * s2).powi(2)).sqrt();
+        let den = ((1.0 + self.a1 * c1 + self.a2 * c2).powi(2) + (self.a1 * s1 + self.a2 * s2).powi(2)).sqrt();
+        num / den
+    }
+}
+
+// cos(w0) and alpha = sin(w0) / 2Q from the cookbook
+fn cookbook_terms(sample_rate: f64, freq_hz: f64, q: f64) -> (f64, f64) {
+    assert!(freq_hz > 0.0 && freq_hz < sample_rate / 2.0, "frequency must be between 0 and Nyquist");
+    let w = 2.0 * PI * freq_hz / sample_rate;
+    (w.cos(), w.sin() / (2.0 * q))
+}
+
+// Cascade of biquads with an overall gain
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sos {
+    pub sections: Vec<Biquad>,
+    pub gain: f64,
+}
+
+impl Sos {
+    pub fn new(sections: Vec<Biquad>, gain: f64) -> Sos {
+        Sos { sections, gain }
+    }
+
+    // Butterworth of any order, prewarped so the -3 dB point lands on `cutoff_hz`
+    pub fn butterworth_lowpass(order: usize, cutoff_hz: f64, sample_rate: f64) -> Sos {
+        let omega = prewarp(cutoff_hz, sample_rate);
+        let poles: Vec<Cx> = butterworth_poles(order).into_iter().map(|p| p * omega).collect();
+        Sos::from_analog_zpk(&[], &poles, sample_rate).normalized_at(0.0, sample_rate, 1.0)
+    }
+
+    pub fn butterworth_highpass(order: usize, cutoff_hz: f64, sample_rate: f64) -> Sos {
+        let omega = prewarp(cutoff_hz, sample_rate);
+        // s -> omega / s: poles invert, every zero at infinity moves to DC
+        let poles: Vec<Cx> = butterworth_poles(order).into_iter().map(|p| Cx::new(omega, 0.0) / p).collect();
+        let zeros = vec![Cx::new(0.0, 0.0); order];
+        Sos::from_analog_zpk(&zeros, &poles, sample_rate).normalized_at(sample_rate / 2.0, sample_rate, 1.0)
+    }
+
+    // Bilinear transform of analogue zeros and poles (rad/s, conjugates included),
+    // with unit gain; zeros at infinity land on Nyquist. Use `normalized_at` to set the gain.
+    pub fn from_analog_zpk(zeros: &[Cx], poles: &[Cx], sample_rate: f64) -> Sos {
+        assert!(zeros.len() <= poles.len(), "more zeros than poles");
+        let k = 2.0 * sample_rate;
+        let to_z = |s: &Cx| (Cx::new(k, 0.0) + *s) / (Cx::new(k, 0.0) - *s);
+        let mut digital_zeros: Vec<Cx> = zeros.iter().map(to_z).collect();
+        digital_zeros.resize(poles.len(), Cx::new(-1.0, 0.0));
+        let digital_poles: Vec<Cx> = poles.iter().map(to_z).collect();
+
+        let zero_pairs = pair_roots(&digital_zeros);
+        let pole_pairs = pair_roots(&digital_poles);
+        let sections = pole_pairs
+            .iter()
+            .enumerate()
+            .map(|(i, &(a1, a2))| {
+                let (b1, b2) = zero_pairs.get(i).cloned().unwrap_or((0.0, 0.0));
+                Biquad::new(1.0, b1, b2, a1, a2)
+            })
+            .collect();
+        Sos::new(sections, 1.0)
+    }
+
+    // Scale the gain so |H(freq_hz)| equals `target`
+    pub fn normalized_at(mut self, freq_hz: f64, sample_rate: f64, target: f64) -> Sos {
+        self.gain = 1.0;
+        self.gain = target / self.magnitude_at(freq_hz, sample_rate);
+        self
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        self.sections.iter_mut().fold(x * self.gain, |acc, section| section.process(acc))
+    }
+
+    // Filter a buffer in place, keeping state across calls
+    pub fn process_buffer(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.process(*sample as f64) as f32;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sections.iter_mut().for_each(Biquad::reset);
+    }
+
+    pub fn magnitude_at(&self, freq_hz: f64, sample_rate: f64) -> f64 {
+        self.sections.iter().map(|s| s.magnitude_at(freq_hz, sample_rate)).product::<f64>() * self.gain.abs()
+    }
+}
+
+fn prewarp(freq_hz: f64, sample_rate: f64) -> f64 {
+    assert!(freq_hz > 0.0 && freq_hz < sample_rate / 2.0, "frequency must be between 0 and Nyquist");
+    2.0 * sample_rate * (PI * freq_hz / sample_rate).tan()
+}
+
+// Unit-cutoff analogue Butterworth poles, all in the left half plane
+fn butterworth_poles(order: usize) -> Vec<Cx> {
+    assert!(order >= 1, "order must be >= 1");
+    (0..order)
+        .map(|k| {
+            let theta = PI * (2 * k + order + 1) as f64 / (2 * order) as f64;
+            Cx::new(theta.cos(), theta.sin())
+        })
+        .collect()
+}
+
+// Group roots into real quadratics (c1, c2) of z^2 + c1 z + c2: conjugate pairs first,
+// then reals two at a time; an odd real is left as a first-order factor (c2 = 0)
+fn pair_roots(roots: &[Cx]) -> Vec<(f64, f64)> {
+    let tolerance = 1e-9;
+    let mut pairs = Vec::new();
+    let mut reals = Vec::new();
+    for root in roots {
+        if root.im.abs() <= tolerance * root.re.abs().max(1.0) {
+            reals.push(root.re);
+        } else if root.im > 0.0 {
+            // the conjugate is implied
+            pairs.push((-2.0 * root.re, root.re * root.re + root.im * root.im));
+        }
+    }
+    reals.sort_by(|a, b| a.total_cmp(b));
+    for chunk in reals.chunks(2) {
+        match chunk {
+            [a, b] => pairs.push((-(a + b), a * b)),
+            [a] => pairs.push((-a, 0.0)),
+            _ => unreachable!(),
+        }
+    }
+    pairs
+}
+
+// All roots of a real polynomial (coefficients lowest order first) by Durand-Kerner
+pub(crate) fn polynomial_roots(coefficients: &[f64]) -> Vec<Cx> {
+    let degree = coefficients.len() - 1;
+    let lead = coefficients[degree];
+    let monic: Vec<f64> = coefficients.iter().map(|c| c / lead).collect();
+    let evaluate = |x: Cx| monic.iter().rev().fold(Cx::new(0.0, 0.0), |acc, &c| acc * x + Cx::new(c, 0.0));
+
+    // Standard start: powers of a number that is neither real nor a root of unity
+    let mut roots: Vec<Cx> = (0..degree).map(|k| Cx::new(0.4, 0.9).powi(k as i32)).collect();
+    for _ in 0..500 {
+        let mut largest_step = 0.0f64;
+        for i in 0..degree {
+            let denominator = (0..degree)
+                .filter(|&j| j != i)
+                .fold(Cx::new(1.0, 0.0), |acc, j| acc * (roots[i] - roots[j]));
+            let step = evaluate(roots[i]) / denominator;
+            roots[i] = roots[i] - step;
+            largest_step = largest_step.max(step.abs());
+        }
+        if largest_step < 1e-14 {
+            break;
+        }
+    }
+    roots
+}
+
+// Minimal f64 complex for filter design (the FFT's Complex is f32)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cx {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Cx {
+    pub fn new(re: f64, im: f64) -> Cx {
+        Cx { re, im }
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    fn powi(self, n: i32) -> Cx {
+        (0..n).fold(Cx::new(1.0, 0.0), |acc, _| acc * self)
+    }
+}
+
+impl Add for Cx {
+    type Output = Cx;
+    fn add(self, other: Cx) -> Cx {
+        Cx::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Cx {
+    type Output = Cx;
+    fn sub(self, other: Cx) -> Cx {
+        Cx::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Cx {
+    type Output = Cx;
+    fn mul(self, other: Cx) -> Cx {
+        Cx::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Mul<f64> for Cx {
+    type Output = Cx;
+    fn mul(self, factor: f64) -> Cx {
+        Cx::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Div for Cx {
+    type Output = Cx;
+    fn div(self, other: Cx) -> Cx {
+        let norm = other.re * other.re + other.im * other.im;
+        Cx::new(
+            (self.re * other.re + self.im * other.im) / norm,
+            (self.im * other.re - self.re * other.im) / norm,
+        )
+    }
+}
+
+// BS.1770 K-weighting: the head-effect high shelf followed by the RLB high-pass.
+// The analogue prototypes are re-derived for any sample rate (same constants as
+// libebur128), which reproduces the 48 kHz coefficients printed in the standard.
+pub fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let pi = PI;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (pi * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        (vh + vb * k / q + k * k) / a0,
+        2.0 * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (pi * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(1.0, -2.0, 1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0);
+
+    [shelf, highpass]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_k_weighting_48k_coefficients() {
+        // Table 1 and 2 of ITU-R BS.1770-4
+        let [shelf, highpass] = k_weighting(48_000.0);
+        assert!((shelf.b0 - 1.53512485958697).abs() < 1e-9);
+        assert!((shelf.b1 + 2.69169618940638).abs() < 1e-9);
+        assert!((shelf.b2 - 1.19839281085285).abs() < 1e-9);
+        assert!((shelf.a1 + 1.69065929318241).abs() < 1e-9);
+        assert!((shelf.a2 - 0.73248077421585).abs() < 1e-9);
+        assert!((highpass.a1 + 1.99004745483398).abs() < 1e-9);
+        assert!((highpass.a2 - 0.99007225036621).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cookbook_designs() {
+        let fs = 48_000.0;
+        let db = |b: &Biquad, f: f64| 20.0 * b.magnitude_at(f, fs).log10();
+        let q = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((db(&Biquad::lowpass(fs, 1000.0, q), 1000.0) + 3.01).abs() < 0.01);
+        assert!(db(&Biquad::lowpass(fs, 1000.0, q), 10.0).abs() < 0.01);
+        assert!((db(&Biquad::highpass(fs, 1000.0, q), 1000.0) + 3.01).abs() < 0.01);
+        assert!((db(&Biquad::peaking(fs, 3000.0, 2.0, 6.0), 3000.0) - 6.0).abs() < 1e-6);
+        assert!(db(&Biquad::peaking(fs, 3000.0, 2.0, 6.0), 100.0).abs() < 0.05);
+        assert!((db(&Biquad::low_shelf(fs, 200.0, q, -9.0), 10.0) + 9.0).abs() < 0.05);
+        assert!((db(&Biquad::low_shelf(fs, 200.0, q, -9.0), 200.0) + 4.5).abs() < 0.01);
+        assert!((db(&Biquad::high_shelf(fs, 8000.0, q, 4.0), 23_000.0) - 4.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_butterworth_cascades() {
+        let fs = 48_000.0;
+        for order in [1, 2, 4, 5] {
+            let lowpass = Sos::butterworth_lowpass(order, 1000.0, fs);
+            let highpass = Sos::butterworth_highpass(order, 1000.0, fs);
+            assert_eq!(lowpass.sections.len(), order.div_ceil(2));
+            let db = |sos: &Sos, f: f64| 20.0 * sos.magnitude_at(f, fs).log10();
+            assert!((db(&lowpass, 1000.0) + 3.01).abs() < 0.01, "order {}", order);
+            assert!((db(&highpass, 1000.0) + 3.01).abs() < 0.01, "order {}", order);
+            // -6N dB per octave well inside the stopband
+            assert!((db(&lowpass, 4000.0) - db(&lowpass, 2000.0) + 6.02 * order as f64).abs() < 0.8, "order {}", order);
+            assert!((db(&highpass, 125.0) - db(&highpass, 250.0) + 6.02 * order as f64).abs() < 0.2, "order {}", order);
+        }
+
+        // Running the filter agrees with the response: a 100 Hz tone passes the lowpass
+        let mut lowpass = Sos::butterworth_lowpass(4, 1000.0, fs);
+        let mut tone: Vec<f32> = (0..48_000).map(|i| (2.0 * PI * 100.0 * i as f64 / fs).sin() as f32).collect();
+        lowpass.process_buffer(&mut tone);
+        let peak = tone[24_000..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 1.0).abs() < 1e-3, "{}", peak);
+    }
+
+    #[test]
+    fn test_polynomial_roots() {
+        // (x - 1)(x + 2)(x^2 + 1) = x^4 + x^3 - x^2 + x - 2
+        let mut roots = polynomial_roots(&[-2.0, 1.0, -1.0, 1.0, 1.0]);
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        let expected = [Cx::new(-2.0, 0.0), Cx::new(0.0, -1.0), Cx::new(0.0, 1.0), Cx::new(1.0, 0.0)];
+        for (root, want) in roots.iter().zip(expected.iter()) {
+            assert!((*root - *want).abs() < 1e-9, "{:?}", roots);
+        }
+    }
+
+    #[test]
+    fn test_k_weighting_response() {
+        for sample_rate in [44_100.0, 48_000.0, 96_000.0] {
+            let [shelf, highpass] = k_weighting(sample_rate);
+            let gain_db = |f: f64| 20.0 * (shelf.magnitude_at(f, sample_rate) * highpass.magnitude_at(f, sample_rate)).log10();
+            // ~+0.7 dB at 1 kHz (hence the -0.691 in the loudness formula), +4 dB shelf, steep low cut
+            assert!((gain_db(1000.0) - 0.691).abs() < 0.02, "{} Hz: {}", sample_rate, gain_db(1000.0));
+            assert!((gain_db(10_000.0) - 4.0).abs() < 0.2);
+            assert!(gain_db(20.0) < -10.0);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Effective bit depth analysis on integer PCM
+// Catches 24/32-bit files that only carry 16 bits of information: zero-padded low bits,
+// 16-bit material run through a gain change (values on a scaled 16-bit lattice), and
+// 16-bit material with fresh dither on top (values clustered around the 16-bit grid).
+// The noise floor spectrum also shows whether the last requantisation used noise shaping.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::amplitude_to_db;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourcePattern {
+    // Low bits carry real information
+    Native,
+    // Low bits always zero, e.g. 16-bit samples shifted into a 24-bit container
+    ZeroPadded { source_bits: u32 },
+    // Values sit on an evenly spaced lattice coarser than the container: a lower
+    // bit depth multiplied by `gain` (e.g. volume-normalised 16-bit)
+    Scaled { source_bits: u32, gain: f32 },
+    // Values cluster around the grid of a lower bit depth with small dither on top
+    Dithered { source_bits: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitDepthAnalysis {
+    pub container_bits: u32,
+    // Container bits minus zero-padded low bits
+    pub used_bits: u32,
+    // Container bits minus log2 of the value lattice step
+    pub effective_bits: f32,
+    pub zero_padded_low_bits: u32,
+    // Bits needed for the largest magnitude, sign included
+    pub peak_bits: u32,
+    // Fraction of samples with each bit set, index 0 = LSB (~0.5 for busy bits)
+    pub bit_activity: Vec<f32>,
+    // Typical distance between neighbouring distinct sample values (1.0 for native audio)
+    pub quantization_step: f32,
+    // Fraction of samples within 1/8 of a grid step of the 16-bit grid (0.25 when unrelated)
+    pub grid_concentration_16: Option<f32>,
+    pub pattern: SourcePattern,
+    // Noise floor level at 15-20 kHz minus 2-8 kHz, when the sample rate allows
+    pub noise_floor_tilt_db: Option<f32>,
+    pub noise_shaped_dither: bool,
+}
+
+pub struct BitDepthAnalyzer {
+    sample_rate: f32,
+    fft_size: usize,
+}
+
+impl BitDepthAnalyzer {
+    pub fn new(sample_rate: f32) -> BitDepthAnalyzer {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        BitDepthAnalyzer {
+            sample_rate,
+            fft_size: 4096,
+        }
+    }
+
+    pub fn with_fft_size(mut self, fft_size: usize) -> Self {
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        self.fft_size = fft_size;
+        self
+    }
+
+    pub fn analyze_i16(&self, samples: &[i16]) -> BitDepthAnalysis {
+        let widened: Vec<i32> = samples.iter().map(|&s| s as i32).collect();
+        self.analyze(&widened, 16)
+    }
+
+    // 24-bit samples sign-extended into i32
+    pub fn analyze_i24(&self, samples: &[i32]) -> BitDepthAnalysis {
+        self.analyze(samples, 24)
+    }
+
+    pub fn analyze_i32(&self, samples: &[i32]) -> BitDepthAnalysis {
+        self.analyze(samples, 32)
+    }
+
+    fn analyze(&self, samples: &[i32], container_bits: u32) -> BitDepthAnalysis {
+        let combined = samples.iter().fold(0i32, |acc, &s| acc | s);
+        let zero_padded_low_bits = if combined == 0 {
+            0
+        } else {
+            combined.trailing_zeros().min(container_bits)
+        };
+        let used_bits = container_bits - zero_padded_low_bits;
+
+        let peak = samples.iter().map(|&s| (s as i64).abs()).max().unwrap_or(0);
+        let peak_bits = if peak == 0 { 0 } else { 64 - peak.leading_zeros() + 1 };
+
+        let bit_activity = (0..container_bits)
+            .map(|bit| {
+                let set = samples.iter().filter(|&&s| (s >> bit) & 1 == 1).count();
+                set as f32 / samples.len().max(1) as f32
+            })
+            .collect();
+
+        let quantization_step = lattice_step(samples);
+        let effective_bits = (container_bits as f32 - quantization_step.max(1.0).log2()).max(0.0);
+
+        let grid_concentration_16 = (container_bits > 16).then(|| grid_concentration(samples, 1 << (container_bits - 16)));
+
+        let pattern = if zero_padded_low_bits > 0 {
+            SourcePattern::ZeroPadded { source_bits: used_bits }
+        } else if quantization_step >= 1.5 {
+            let source_bits = container_bits.saturating_sub(quantization_step.log2().round() as u32);
+            let nominal_step = (1u64 << (container_bits - source_bits)) as f32;
+            SourcePattern::Scaled {
+                source_bits,
+                gain: quantization_step / nominal_step,
+            }
+        } else if grid_concentration_16.is_some_and(|c| c > 0.6) {
+            SourcePattern::Dithered { source_bits: 16 }
+        } else {
+            SourcePattern::Native
+        };
+
+        let full_scale = (1u64 << (container_bits - 1)) as f32;
+        let normalised: Vec<f32> = samples.iter().map(|&s| s as f32 / full_scale).collect();
+        let noise_floor_tilt_db = self.noise_floor_tilt(&normalised);
+
+        BitDepthAnalysis {
+            container_bits,
+            used_bits,
+            effective_bits,
+            zero_padded_low_bits,
+            peak_bits,
+            bit_activity,
+            quantization_step,
+            grid_concentration_16,
+            pattern,
+            noise_floor_tilt_db,
+            noise_shaped_dither: noise_floor_tilt_db.is_some_and(|tilt| tilt > 10.0),
+        }
+    }
+
+    // Per-bin 10th percentile across frames approximates the noise floor; noise shaped
+    // dither pushes it up steeply towards the top of the band
+    fn noise_floor_tilt(&self, audio_data: &[f32]) -> Option<f32> {
+        let nyquist = self.sample_rate / 2.0;
+        if nyquist < 16_000.0 {
+            return None;
+        }
+        let mut processor = SpectrogramProcessor::new(self.fft_size);
+        let (frames, num_windows, freq_bins) = processor.process_windows(audio_data, 0.5);
+        if num_windows < 4 {
+            return None;
+        }
+
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let floor_level = |lo_hz: f32, hi_hz: f32| {
+            let (lo, hi) = ((lo_hz / bin_hz) as usize, ((hi_hz / bin_hz) as usize).min(freq_bins));
+            let mut total = 0.0f32;
+            let mut column = Vec::with_capacity(num_windows);
+            for bin in lo..hi {
+                column.clear();
+                column.extend(frames.chunks_exact(freq_bins).map(|frame| amplitude_to_db(frame[bin])));
+                column.sort_by(|a, b| a.total_cmp(b));
+                total += column[column.len() / 10];
+            }
+            total / (hi - lo).max(1) as f32
+        };
+
+        Some(floor_level(15_000.0, 20_000.0f32.min(nyquist)) - floor_level(2_000.0, 8_000.0))
+    }
+}
+
+// Most common small gap between neighbouring distinct values, averaged with its ±1
+// neighbours so a fractional lattice (e.g. 230/231 alternating) keeps its fraction
+fn lattice_step(samples: &[i32]) -> f32 {
+    let mut distinct: Vec<i32> = samples.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() < 3 {
+        return 1.0;
+    }
+
+    let gaps: Vec<u32> = distinct.windows(2).map(|w| (w[1] as i64 - w[0] as i64) as u32).collect();
+    let mut counts = std::collections::HashMap::new();
+    for &gap in &gaps {
+        *counts.entry(gap).or_insert(0usize) += 1;
+    }
+    // Smallest gap that is common (>= 1% of gaps), not just the most frequent one, so
+    // sparse loud passages with big gaps cannot outvote the dense region near zero
+    let threshold = (gaps.len() / 100).max(2);
+    let Some(&mode) = counts.iter().filter(|(_, &c)| c >= threshold).map(|(g, _)| g).min() else {
+        return 1.0;
+    };
+
+    let near: Vec<u32> = gaps.iter().cloned().filter(|&g| g + 1 >= mode && g <= mode + 1).collect();
+    near.iter().map(|&g| g as f32).sum::<f32>() / near.len() as f32
+}
+
+// Fraction of samples within grid / 8 of a multiple of `grid`
+fn grid_concentration(samples: &[i32], grid: i32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let near = samples
+        .iter()
+        .filter(|&&s| {
+            let residual = s.rem_euclid(grid);
+            residual.min(grid - residual) < grid / 8
+        })
+        .count();
+    near as f32 / samples.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::white_noise;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+    const LEN: usize = 1 << 16;
+
+    // Music-like test material: decaying sines plus noise, full scale = 1.0
+    fn program(len: usize) -> Vec<f32> {
+        let noise = white_noise(len, 0.05, 8);
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE;
+                let envelope = 0.5 * (1.0 + (2.0 * PI * 0.7 * t).cos()) + 0.001;
+                envelope * (0.3 * (2.0 * PI * 440.0 * t).sin() + 0.1 * (2.0 * PI * 1330.0 * t).sin()) + noise[i]
+            })
+            .collect()
+    }
+
+    // TPDF dither at the target LSB, then round
+    fn quantise(signal: &[f32], bits: u32, seed: u64) -> Vec<i32> {
+        let scale = (1i64 << (bits - 1)) as f32;
+        let a = white_noise(signal.len(), 0.5, seed);
+        let b = white_noise(signal.len(), 0.5, seed + 1);
+        signal
+            .iter()
+            .zip(a.iter().zip(b.iter()))
+            .map(|(&s, (&d1, &d2))| (s * scale + d1 + d2).round() as i32)
+            .collect()
+    }
+
+    #[test]
+    fn test_native_24_bit() {
+        let samples = quantise(&program(LEN), 24, 1);
+        let analysis = BitDepthAnalyzer::new(SAMPLE_RATE).analyze_i24(&samples);
+
+        assert_eq!(analysis.zero_padded_low_bits, 0);
+        assert_eq!(analysis.used_bits, 24);
+        assert_eq!(analysis.pattern, SourcePattern::Native);
+        assert!(analysis.effective_bits > 23.0, "{}", analysis.effective_bits);
+        assert!((analysis.bit_activity[0] - 0.5).abs() < 0.05);
+        assert!(!analysis.noise_shaped_dither);
+    }
+
+    #[test]
+    fn test_zero_padded_16_in_24() {
+        let samples: Vec<i32> = quantise(&program(LEN), 16, 2).iter().map(|&s| s << 8).collect();
+        let analysis = BitDepthAnalyzer::new(SAMPLE_RATE).analyze_i24(&samples);
+
+        assert_eq!(analysis.zero_padded_low_bits, 8);
+        assert_eq!(analysis.used_bits, 16);
+        assert_eq!(analysis.pattern, SourcePattern::ZeroPadded { source_bits: 16 });
+        assert!(analysis.bit_activity[..8].iter().all(|&a| a == 0.0));
+        assert!((analysis.effective_bits - 16.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_scaled_16_bit() {
+        // 16-bit master turned down by 0.9 in a 24-bit editor, no dither
+        let samples: Vec<i32> = quantise(&program(LEN), 16, 3)
+            .iter()
+            .map(|&s| (s as f32 * 256.0 * 0.9).round() as i32)
+            .collect();
+        let analysis = BitDepthAnalyzer::new(SAMPLE_RATE).analyze_i24(&samples);
+
+        assert_eq!(analysis.zero_padded_low_bits, 0);
+        match analysis.pattern {
+            SourcePattern::Scaled { source_bits, gain } => {
+                assert_eq!(source_bits, 16);
+                assert!((gain - 0.9).abs() < 0.01, "gain {}", gain);
+            }
+            other => panic!("expected scaled pattern, got {:?}", other),
+        }
+        assert!((analysis.effective_bits - 16.15).abs() < 0.1, "{}", analysis.effective_bits);
+    }
+
+    #[test]
+    fn test_dithered_16_bit() {
+        // 16-bit master converted to 24-bit with a couple of 24-bit LSBs of fresh dither
+        let dither = white_noise(LEN, 6.0, 4);
+        let samples: Vec<i32> = quantise(&program(LEN), 16, 5)
+            .iter()
+            .zip(dither.iter())
+            .map(|(&s, &d)| (s << 8) + d.round() as i32)
+            .collect();
+        let analysis = BitDepthAnalyzer::new(SAMPLE_RATE).analyze_i24(&samples);
+
+        assert_eq!(analysis.zero_padded_low_bits, 0);
+        assert!(analysis.grid_concentration_16.unwrap() > 0.9);
+        assert_eq!(analysis.pattern, SourcePattern::Dithered { source_bits: 16 });
+    }
+
+    #[test]
+    fn test_noise_shaped_16_bit() {
+        // Quiet tone requantised to 16 bits with second-order error feedback: E(z)(1 - z^-1)^2
+        // (a bare tone, so nothing but the requantisation error sets the floor)
+        let quiet: Vec<f32> = (0..LEN).map(|i| 0.0003 * (2.0 * PI * 440.0 * i as f32 / SAMPLE_RATE).sin()).collect();
+        let dither = white_noise(LEN, 0.5, 6);
+        let (mut e1, mut e2) = (0.0f32, 0.0f32);
+        let shaped: Vec<i16> = quiet
+            .iter()
+            .zip(dither.iter())
+            .map(|(&s, &d)| {
+                let wanted = s * 32768.0 - (2.0 * e1 - e2);
+                let out = (wanted + d).round();
+                e2 = e1;
+                e1 = out - wanted;
+                out as i16
+            })
+            .collect();
+        let flat: Vec<i16> = quantise(&quiet, 16, 7).iter().map(|&s| s as i16).collect();
+
+        let analyzer = BitDepthAnalyzer::new(SAMPLE_RATE);
+        let shaped_analysis = analyzer.analyze_i16(&shaped);
+        let flat_analysis = analyzer.analyze_i16(&flat);
+
+        assert!(shaped_analysis.noise_shaped_dither, "tilt {:?}", shaped_analysis.noise_floor_tilt_db);
+        assert!(!flat_analysis.noise_shaped_dither, "tilt {:?}", flat_analysis.noise_floor_tilt_db);
+        assert_eq!(shaped_analysis.container_bits, 16);
+        assert!(shaped_analysis.grid_concentration_16.is_none());
+    }
+}
@@ -0,0 +1,193 @@
+// Clipping detection: runs of consecutive full-scale samples (hard clipping in the
+// file itself) and inter-sample overs (samples below full scale whose reconstructed
+// waveform goes above it, found on the 4x true-peak oversampled signal)
+use crate::db::amplitude_to_db;
+use crate::true_peak::{oversample_4x, INTERPOLATOR_DELAY};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClipKind {
+    FullScaleRun,
+    InterSampleOver,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipEvent {
+    pub kind: ClipKind,
+    // First affected input sample and the event length in input samples
+    // (inter-sample overs are located to the nearest sample)
+    pub start_sample: usize,
+    pub length: usize,
+    pub start_seconds: f32,
+    // Largest absolute value within the event (oversampled for overs)
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClippingAnalysis {
+    pub events: Vec<ClipEvent>,
+    pub clipped_samples: usize,
+    pub full_scale_runs: usize,
+    pub inter_sample_overs: usize,
+    pub sample_peak_dbfs: f32,
+    pub true_peak_dbtp: f32,
+}
+
+pub struct ClippingDetector {
+    sample_rate: f32,
+    // |x| at or above this counts as full scale (default: within one 16-bit LSB of 1.0)
+    full_scale: f32,
+    min_run: usize,
+    // Oversampled values above this are overs (default 1.0 = 0 dBTP)
+    over_threshold: f32,
+}
+
+impl ClippingDetector {
+    pub fn new(sample_rate: f32) -> ClippingDetector {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        ClippingDetector {
+            sample_rate,
+            full_scale: 1.0 - 1.0 / 32768.0,
+            min_run: 3,
+            over_threshold: 1.0,
+        }
+    }
+
+    // For material that was clipped and then turned down, pass the level it sits at
+    pub fn with_full_scale(mut self, full_scale: f32, min_run: usize) -> Self {
+        assert!(min_run >= 1, "min_run must be >= 1");
+        self.full_scale = full_scale;
+        self.min_run = min_run;
+        self
+    }
+
+    pub fn with_over_threshold_dbtp(mut self, dbtp: f32) -> Self {
+        self.over_threshold = 10f32.powf(dbtp / 20.0);
+        self
+    }
+
+    pub fn analyze(&self, samples: &[f32]) -> ClippingAnalysis {
+        let mut events = Vec::new();
+        let mut clipped = vec![false; samples.len()];
+
+        let mut i = 0;
+        while i < samples.len() {
+            if samples[i].abs() < self.full_scale {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let positive = samples[i] > 0.0;
+            while i < samples.len() && samples[i].abs() >= self.full_scale && (samples[i] > 0.0) == positive {
+                i += 1;
+            }
+            if i - start >= self.min_run {
+                clipped[start..i].iter_mut().for_each(|c| *c = true);
+                events.push(self.event(ClipKind::FullScaleRun, start, i - start, &samples[start..i]));
+            }
+        }
+
+        let oversampled = oversample_4x(samples);
+        let true_peak = oversampled.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        let position = |j: usize| (j / 4).saturating_sub(INTERPOLATOR_DELAY).min(samples.len().saturating_sub(1));
+        let mut j = 0;
+        while j < oversampled.len() {
+            if oversampled[j].abs() <= self.over_threshold {
+                j += 1;
+                continue;
+            }
+            let start = j;
+            while j < oversampled.len() && oversampled[j].abs() > self.over_threshold {
+                j += 1;
+            }
+            let (first, last) = (position(start), position(j - 1));
+            // Overs next to a clipped run are part of that run, not a separate finding
+            let lo = first.saturating_sub(1);
+            let hi = (last + 2).min(samples.len());
+            if clipped[lo..hi].iter().any(|&c| c) {
+                continue;
+            }
+            events.push(self.event(ClipKind::InterSampleOver, first, last - first + 1, &oversampled[start..j]));
+        }
+        events.sort_by_key(|e| e.start_sample);
+
+        let sample_peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        ClippingAnalysis {
+            clipped_samples: clipped.iter().filter(|&&c| c).count(),
+            full_scale_runs: events.iter().filter(|e| e.kind == ClipKind::FullScaleRun).count(),
+            inter_sample_overs: events.iter().filter(|e| e.kind == ClipKind::InterSampleOver).count(),
+            events,
+            sample_peak_dbfs: amplitude_to_db(sample_peak),
+            true_peak_dbtp: amplitude_to_db(true_peak),
+        }
+    }
+
+    fn event(&self, kind: ClipKind, start_sample: usize, length: usize, values: &[f32]) -> ClipEvent {
+        ClipEvent {
+            kind,
+            start_sample,
+            length,
+            start_seconds: start_sample as f32 / self.sample_rate,
+            peak: values.iter().fold(0.0f32, |p, s| p.max(s.abs())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_hard_clipped_sine() {
+        // 100 Hz sine driven 6 dB into the limiter: one flat top per half cycle
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (2.0 * (2.0 * PI * 100.0 * i as f32 / SAMPLE_RATE).sin()).clamp(-1.0, 1.0))
+            .collect();
+        let analysis = ClippingDetector::new(SAMPLE_RATE).analyze(&samples);
+
+        assert_eq!(analysis.full_scale_runs, 20);
+        assert_eq!(analysis.inter_sample_overs, 0);
+        // 2 sin > 1 from 30 to 150 degrees: of a 480 sample period, runs start at 40 and last 160
+        let first = &analysis.events[0];
+        assert_eq!(first.kind, ClipKind::FullScaleRun);
+        assert!((first.start_sample as i32 - 40).abs() <= 1, "{}", first.start_sample);
+        assert!((first.length as i32 - 160).abs() <= 2, "{}", first.length);
+        assert!((first.start_seconds - 40.0 / SAMPLE_RATE).abs() < 1e-4);
+        assert!(analysis.clipped_samples > 3000);
+    }
+
+    #[test]
+    fn test_inter_sample_overs() {
+        // Samples peak at -1 dBFS but the fs/4 waveform between them reaches +2 dBTP,
+        // for 100 samples in the middle of silence
+        let samples: Vec<f32> = (0..1000)
+            .map(|i| {
+                if (500..600).contains(&i) {
+                    1.26 * (PI / 2.0 * i as f32 + PI / 4.0).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let analysis = ClippingDetector::new(SAMPLE_RATE).analyze(&samples);
+
+        assert_eq!(analysis.full_scale_runs, 0);
+        assert!(analysis.inter_sample_overs > 10);
+        assert!(analysis.sample_peak_dbfs < 0.0);
+        assert!((analysis.true_peak_dbtp - 2.0).abs() < 0.4, "{}", analysis.true_peak_dbtp);
+        assert!(analysis.events.iter().all(|e| (499..601).contains(&e.start_sample)));
+    }
+
+    #[test]
+    fn test_clean_signal() {
+        let samples: Vec<f32> = (0..4800).map(|i| 0.8 * (2.0 * PI * 440.0 * i as f32 / SAMPLE_RATE).sin()).collect();
+        let analysis = ClippingDetector::new(SAMPLE_RATE).analyze(&samples);
+        assert!(analysis.events.is_empty());
+        assert_eq!(analysis.clipped_samples, 0);
+    }
+}
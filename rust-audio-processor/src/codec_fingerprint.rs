@@ -0,0 +1,470 @@
+// Source codec fingerprinting from spectral signatures
+// Builds on the transcode lowpass estimate and scores the `process_windows` frames against
+// marks individual encoders leave behind:
+// - MP3 (LAME): sfb21 (the band above ~16 kHz) switched on and off from granule to granule
+// - AAC: whole scale factor bands quantised to zero ("spectral holes") inside the passband
+// - HE-AAC: SBR copies the low band upwards, so the fine structure repeats above the crossover
+// - Opus/Vorbis: per-frame band energy quantisation, seen as level steps at fixed band edges
+// These are heuristics: they rank candidates, they do not prove provenance.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::{amplitude_to_db, power_to_db, FLOOR_DB};
+use crate::transcode::{lame_kbps_for_cutoff, long_term_spectrum_db, TranscodeDetector, TranscodeVerdict};
+use crate::utils::correlation;
+
+// Opus CELT band edges (Hz), also close to where Vorbis floors step
+const CELT_BAND_EDGES_HZ: [f32; 21] = [
+    200.0, 400.0, 600.0, 800.0, 1000.0, 1200.0, 1400.0, 1600.0, 2000.0, 2400.0, 2800.0, 3200.0, 4000.0,
+    4800.0, 5600.0, 6800.0, 8000.0, 9600.0, 12000.0, 15600.0, 20000.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourceCodec {
+    Mp3,
+    Aac,
+    HeAac,
+    OpusOrVorbis,
+    // No lossy signature found (lossless source or an unknown encoder)
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitrateClass {
+    // <= 96 kbps
+    Low,
+    // 112 - 160 kbps
+    Medium,
+    // 192 - 256 kbps
+    High,
+    // 320 kbps and up
+    VeryHigh,
+    Unknown,
+}
+
+impl BitrateClass {
+    pub fn from_kbps(kbps: u32) -> BitrateClass {
+        match kbps {
+            0..=96 => BitrateClass::Low,
+            97..=160 => BitrateClass::Medium,
+            161..=256 => BitrateClass::High,
+            _ => BitrateClass::VeryHigh,
+        }
+    }
+}
+
+// Raw signature measurements, kept so reports can show why a codec won
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignatureFeatures {
+    pub cutoff_hz: Option<f32>,
+    // 0 when the top band is steady, 1 when it is on in half of the frames
+    pub sfb21_intermittency: f32,
+    // Fraction of passband bands per frame sitting 30 dB under the frame's median band
+    pub hole_rate: f32,
+    // Best mean correlation between a band above a crossover and a lower band
+    pub sbr_correlation: f32,
+    pub sbr_crossover_hz: Option<f32>,
+    // Level jumps at CELT band edges relative to jumps inside the bands (about 1 for natural audio)
+    pub band_step_ratio: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodecScores {
+    pub mp3: f32,
+    pub aac: f32,
+    pub he_aac: f32,
+    pub opus_vorbis: f32,
+    pub none: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodecFingerprint {
+    pub codec: SourceCodec,
+    pub bitrate_class: BitrateClass,
+    pub estimated_kbps: Option<u32>,
+    // Winning score over the sum of scores
+    pub confidence: f32,
+    pub scores: CodecScores,
+    pub features: SignatureFeatures,
+}
+
+// Typical AAC-LC (Apple/FDK) lowpass per bitrate
+fn aac_kbps_for_cutoff(cutoff_hz: f32) -> u32 {
+    match cutoff_hz {
+        c if c < 14_000.0 => 96,
+        c if c < 16_500.0 => 128,
+        c if c < 18_500.0 => 192,
+        _ => 256,
+    }
+}
+
+fn band_level_db(frame: &[f32], lo: usize, hi: usize) -> f32 {
+    let hi = hi.min(frame.len());
+    if lo >= hi {
+        return FLOOR_DB;
+    }
+    power_to_db(frame[lo..hi].iter().map(|&m| m * m).sum::<f32>() / (hi - lo) as f32)
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return FLOOR_DB;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
+
+pub struct CodecClassifier {
+    sample_rate: f32,
+    fft_size: usize,
+}
+
+impl CodecClassifier {
+    pub fn new(sample_rate: f32, fft_size: usize) -> CodecClassifier {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        CodecClassifier { sample_rate, fft_size }
+    }
+
+    fn bin(&self, hz: f32) -> usize {
+        (hz * self.fft_size as f32 / self.sample_rate).round() as usize
+    }
+
+    // Run the spectrogram and classify it (mono PCM)
+    pub fn classify(&self, audio_data: &[f32]) -> CodecFingerprint {
+        let mut processor = SpectrogramProcessor::new(self.fft_size);
+        let (frames, _, freq_bins) = processor.process_windows(audio_data, 0.5);
+        self.classify_spectrogram(&frames, freq_bins)
+    }
+
+    // Classify existing `process_windows` output (freq_stride 1, same fft_size)
+    pub fn classify_spectrogram(&self, frames: &[f32], freq_bins: usize) -> CodecFingerprint {
+        assert_eq!(freq_bins, self.fft_size / 2, "spectrogram must come from the classifier's fft_size");
+        let transcode = TranscodeDetector::new(self.sample_rate, self.fft_size)
+            .analyze_long_term_spectrum(&long_term_spectrum_db(frames, freq_bins));
+
+        // Only frames with signal carry a signature
+        let active: Vec<&[f32]> = frames
+            .chunks_exact(freq_bins)
+            .filter(|frame| band_level_db(frame, 1, freq_bins) > -100.0)
+            .collect();
+
+        let lossy_cutoff = match transcode.verdict {
+            TranscodeVerdict::Lossy { cutoff_hz, .. } => Some(cutoff_hz),
+            _ => None,
+        };
+        let band_top_hz = lossy_cutoff.unwrap_or(self.sample_rate / 2.0 - 500.0);
+
+        let (sbr_correlation, sbr_crossover_hz) = self.sbr_correlation(&active, band_top_hz);
+        let features = SignatureFeatures {
+            cutoff_hz: lossy_cutoff,
+            sfb21_intermittency: lossy_cutoff.map_or(0.0, |c| self.sfb21_intermittency(&active, c)),
+            hole_rate: self.hole_rate(&active, band_top_hz),
+            sbr_correlation,
+            sbr_crossover_hz,
+            band_step_ratio: self.band_step_ratio(&active, band_top_hz),
+        };
+
+        let lossy = if lossy_cutoff.is_some() { transcode.confidence } else { 0.0 };
+        let sbr = ((features.sbr_correlation - 0.4) / 0.4).clamp(0.0, 1.0);
+        let holes = (features.hole_rate / 0.05).clamp(0.0, 1.0);
+        let steps = ((features.band_step_ratio - 1.3) / 0.7).clamp(0.0, 1.0);
+        let sfb21 = features.sfb21_intermittency;
+
+        // A bare lowpass with no other marks is most often MP3, so it keeps a base score
+        let scores = CodecScores {
+            mp3: lossy * (0.35 + 0.65 * sfb21) * (1.0 - holes) * (1.0 - sbr) * (1.0 - steps),
+            aac: lossy.max(0.5) * holes * (1.0 - sfb21) * (1.0 - sbr),
+            he_aac: sbr,
+            opus_vorbis: steps * (1.0 - sbr),
+            none: if transcode.verdict == TranscodeVerdict::FullBand {
+                transcode.confidence * (1.0 - sbr.max(holes).max(steps))
+            } else {
+                0.05
+            },
+        };
+
+        let ranked = [
+            (SourceCodec::Mp3, scores.mp3),
+            (SourceCodec::Aac, scores.aac),
+            (SourceCodec::HeAac, scores.he_aac),
+            (SourceCodec::OpusOrVorbis, scores.opus_vorbis),
+            (SourceCodec::None, scores.none),
+        ];
+        let (codec, best) = ranked
+            .iter()
+            .cloned()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((SourceCodec::None, 0.0));
+        let total: f32 = ranked.iter().map(|(_, s)| s).sum();
+        let confidence = if total > 0.0 { best / total } else { 0.0 };
+
+        let estimated_kbps = match (codec, lossy_cutoff) {
+            (SourceCodec::Mp3, Some(c)) => Some(lame_kbps_for_cutoff(c)),
+            (SourceCodec::Aac, Some(c)) => Some(aac_kbps_for_cutoff(c)),
+            (SourceCodec::HeAac, _) => Some(64),
+            _ => None,
+        };
+
+        CodecFingerprint {
+            codec,
+            bitrate_class: estimated_kbps.map_or(BitrateClass::Unknown, BitrateClass::from_kbps),
+            estimated_kbps,
+            confidence,
+            scores,
+            features,
+        }
+    }
+
+    // Per frame, compare the top kHz under the cutoff with a reference band below it.
+    // LAME leaves sfb21 either present (~0 dB) or gone (far below) in different frames.
+    fn sfb21_intermittency(&self, frames: &[&[f32]], cutoff_hz: f32) -> f32 {
+        if frames.is_empty() || cutoff_hz < 4000.0 {
+            return 0.0;
+        }
+        let top = (self.bin(cutoff_hz - 1000.0), self.bin(cutoff_hz - 100.0));
+        let reference = (self.bin(cutoff_hz - 3000.0), self.bin(cutoff_hz - 1500.0));
+
+        let (mut off, mut on) = (0usize, 0usize);
+        for frame in frames {
+            let delta = band_level_db(frame, top.0, top.1) - band_level_db(frame, reference.0, reference.1);
+            if delta < -15.0 {
+                off += 1;
+            } else if delta > -6.0 {
+                on += 1;
+            }
+        }
+        if on == 0 || off == 0 {
+            return 0.0;
+        }
+        let off_fraction = off as f32 / frames.len() as f32;
+        (2.0 * off_fraction.min(1.0 - off_fraction)).clamp(0.0, 1.0)
+    }
+
+    // Fraction of ~86 Hz bands between 3 kHz and the cutoff that collapse to a hole.
+    // Narrower than most scale factor bands so a zeroed band fully covers some of them.
+    fn hole_rate(&self, frames: &[&[f32]], band_top_hz: f32) -> f32 {
+        let width = self.bin(86.0).max(2);
+        let lo = self.bin(3000.0);
+        let hi = self.bin(band_top_hz - 500.0);
+        if frames.is_empty() || hi <= lo + 4 * width {
+            return 0.0;
+        }
+
+        let (mut holes, mut total) = (0usize, 0usize);
+        let mut levels = Vec::new();
+        for frame in frames {
+            levels.clear();
+            levels.extend((lo..hi - width).step_by(width).map(|b| band_level_db(frame, b, b + width)));
+            let mut sorted = levels.clone();
+            let frame_median = median(&mut sorted);
+            holes += levels.iter().filter(|&&l| l < frame_median - 30.0).count();
+            total += levels.len();
+        }
+        holes as f32 / total.max(1) as f32
+    }
+
+    // Search crossover c (4-14 kHz, 500 Hz steps) and copy distance s for the strongest
+    // per-frame correlation between the dB fine structure of [c, c + 1 kHz) and [c - s, c - s + 1 kHz)
+    fn sbr_correlation(&self, frames: &[&[f32]], band_top_hz: f32) -> (f32, Option<f32>) {
+        let width = self.bin(1000.0);
+        let min_shift = self.bin(1000.0);
+        let lowest_source = self.bin(500.0);
+        let max_crossover = (band_top_hz - 1000.0).min(14_000.0);
+        if frames.is_empty() || width < 8 || max_crossover < 4000.0 {
+            return (0.0, None);
+        }
+
+        // A dozen frames spread over the file keep the search cheap
+        let step = (frames.len() / 12).max(1);
+        let sampled: Vec<Vec<f32>> = frames
+            .iter()
+            .step_by(step)
+            .take(12)
+            .map(|frame| frame.iter().map(|&m| amplitude_to_db(m)).collect())
+            .collect();
+
+        // Best correlation per candidate crossover
+        let mut per_crossover = Vec::new();
+        let mut crossover_hz = 4000.0;
+        while crossover_hz <= max_crossover {
+            let c = self.bin(crossover_hz);
+            let mut best = 0.0f32;
+            for shift in min_shift..=c - lowest_source {
+                let source = c - shift;
+                let mean_corr = sampled
+                    .iter()
+                    .map(|db| correlation(&db[c..c + width], &db[source..source + width]))
+                    .sum::<f32>()
+                    / sampled.len() as f32;
+                best = best.max(mean_corr);
+            }
+            per_crossover.push((crossover_hz, best));
+            crossover_hz += 500.0;
+        }
+
+        // Every candidate inside the patched region correlates; the crossover is the lowest one
+        let best = per_crossover.iter().map(|&(_, corr)| corr).fold(0.0f32, f32::max);
+        if best < 0.4 {
+            return (best, None);
+        }
+        let crossover = per_crossover
+            .iter()
+            .find(|&&(_, corr)| corr >= best - 0.05)
+            .map(|&(hz, _)| hz);
+        (best, crossover)
+    }
+
+    // Mean |level step| across CELT band edges over mean |step| at band midpoints
+    fn band_step_ratio(&self, frames: &[&[f32]], band_top_hz: f32) -> f32 {
+        let mut edges: Vec<usize> = CELT_BAND_EDGES_HZ
+            .iter()
+            .filter(|&&hz| hz < band_top_hz - 200.0)
+            .map(|&hz| self.bin(hz))
+            .collect();
+        edges.insert(0, 0);
+        if frames.is_empty() || edges.len() < 4 {
+            return 1.0;
+        }
+
+        let (mut edge_sum, mut mid_sum, mut count) = (0.0f32, 0.0f32, 0usize);
+        for pair in edges.windows(3) {
+            let (below, edge, above) = (pair[0], pair[1], pair[2]);
+            // Compare up to 8 bins either side, never reaching past the neighbouring edges
+            let side = ((edge - below) / 2).min((above - edge) / 2).min(8);
+            if side < 2 {
+                continue;
+            }
+            let mid = (edge + above) / 2;
+            for frame in frames {
+                let step = |at: usize| (band_level_db(frame, at - side, at) - band_level_db(frame, at, at + side)).abs();
+                edge_sum += step(edge);
+                mid_sum += step(mid);
+                count += 1;
+            }
+        }
+        if count == 0 || mid_sum <= 0.0 {
+            return 1.0;
+        }
+        edge_sum / mid_sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{brickwall_lowpass, edit_blocks, mix, pseudo_random, white_noise};
+    use crate::utils::Complex;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+    const FFT_SIZE: usize = 4096;
+    const LEN: usize = 1 << 17;
+
+    fn hz_to_bin(hz: f32, size: usize) -> usize {
+        (hz * size as f32 / SAMPLE_RATE).round() as usize
+    }
+
+    fn lowpassed(cutoff_hz: f32, seed: u64) -> Vec<f32> {
+        brickwall_lowpass(&white_noise(LEN, 0.5, seed), cutoff_hz, SAMPLE_RATE)
+    }
+
+    fn with_floor(signal: &[f32]) -> Vec<f32> {
+        mix(signal, &white_noise(signal.len(), 1e-5, 1234))
+    }
+
+    fn classify(signal: &[f32]) -> CodecFingerprint {
+        CodecClassifier::new(SAMPLE_RATE, FFT_SIZE).classify(signal)
+    }
+
+    #[test]
+    fn test_mp3_sfb21_toggling() {
+        // 128k LAME style: 17 kHz lowpass, 16-17 kHz present only in some granules
+        let (lo, hi) = (hz_to_bin(16_000.0, FFT_SIZE), hz_to_bin(17_000.0, FFT_SIZE));
+        let signal = edit_blocks(&lowpassed(17_000.0, 5), FFT_SIZE, |block, bins| {
+            if pseudo_random(block, 1).is_multiple_of(2) {
+                bins[lo..hi].iter_mut().for_each(|b| *b = Complex::new(0.0, 0.0));
+            }
+        });
+
+        let fingerprint = classify(&with_floor(&signal));
+        assert!(fingerprint.features.sfb21_intermittency > 0.5, "{:?}", fingerprint.features);
+        assert_eq!(fingerprint.codec, SourceCodec::Mp3, "{:?}", fingerprint.scores);
+        assert_eq!(fingerprint.estimated_kbps, Some(128));
+        assert_eq!(fingerprint.bitrate_class, BitrateClass::Medium);
+    }
+
+    #[test]
+    fn test_aac_spectral_holes() {
+        // 256k AAC style: 19.5 kHz lowpass with scale factor bands zeroed at random
+        let band = hz_to_bin(200.0, FFT_SIZE);
+        let (lo, hi) = (hz_to_bin(3000.0, FFT_SIZE), hz_to_bin(19_000.0, FFT_SIZE));
+        let signal = edit_blocks(&lowpassed(19_500.0, 9), FFT_SIZE, |block, bins| {
+            for (i, start) in (lo..hi).step_by(band).enumerate() {
+                if pseudo_random(block * 1000 + i, 2).is_multiple_of(8) {
+                    bins[start..start + band].iter_mut().for_each(|b| *b = Complex::new(0.0, 0.0));
+                }
+            }
+        });
+
+        let fingerprint = classify(&with_floor(&signal));
+        assert!(fingerprint.features.hole_rate > 0.03, "{:?}", fingerprint.features);
+        assert_eq!(fingerprint.codec, SourceCodec::Aac, "{:?}", fingerprint.scores);
+        assert_eq!(fingerprint.estimated_kbps, Some(256));
+        assert_eq!(fingerprint.bitrate_class, BitrateClass::High);
+    }
+
+    #[test]
+    fn test_he_aac_copy_up() {
+        // SBR style: 4-8 kHz copied up to 8-12 kHz, nothing above 12 kHz
+        let core = lowpassed(8_000.0, 13);
+        let shift = hz_to_bin(4_000.0, LEN) / (LEN / FFT_SIZE) * (LEN / FFT_SIZE);
+        let (lo, hi) = (hz_to_bin(4_000.0, LEN), hz_to_bin(8_000.0, LEN));
+        let signal = edit_blocks(&core, LEN, |_, bins| {
+            for k in lo..hi {
+                bins[k + shift] = bins[k];
+            }
+        });
+
+        let fingerprint = classify(&with_floor(&signal));
+        assert!(fingerprint.features.sbr_correlation > 0.8, "{:?}", fingerprint.features);
+        assert_eq!(fingerprint.features.sbr_crossover_hz, Some(8_000.0));
+        assert_eq!(fingerprint.codec, SourceCodec::HeAac, "{:?}", fingerprint.scores);
+        assert_eq!(fingerprint.bitrate_class, BitrateClass::Low);
+    }
+
+    #[test]
+    fn test_opus_band_energy_steps() {
+        // CELT style: every block gets its own quantised gain per band
+        let mut edges: Vec<usize> = vec![0];
+        edges.extend(CELT_BAND_EDGES_HZ.iter().map(|&hz| hz_to_bin(hz, FFT_SIZE)));
+        let signal = edit_blocks(&lowpassed(20_000.0, 17), FFT_SIZE, |block, bins| {
+            for (band, pair) in edges.windows(2).enumerate() {
+                let steps = (pseudo_random(block * 100 + band, 3) % 5) as f32 - 2.0;
+                let gain = 10f32.powf(3.0 * steps / 20.0);
+                bins[pair[0]..pair[1]].iter_mut().for_each(|b| {
+                    b.real *= gain;
+                    b.imag *= gain;
+                });
+            }
+        });
+
+        let fingerprint = classify(&with_floor(&signal));
+        assert!(fingerprint.features.band_step_ratio > 1.6, "{:?}", fingerprint.features);
+        assert_eq!(fingerprint.codec, SourceCodec::OpusOrVorbis, "{:?}", fingerprint.scores);
+    }
+
+    #[test]
+    fn test_full_band_noise_has_no_codec() {
+        let fingerprint = classify(&white_noise(LEN, 0.5, 21));
+        let features = &fingerprint.features;
+        assert!(features.sbr_correlation < 0.4, "{:?}", features);
+        assert_eq!(features.sbr_crossover_hz, None);
+        assert!(features.hole_rate < 0.01, "{:?}", features);
+        assert!(features.band_step_ratio < 1.3, "{:?}", features);
+        assert_eq!(fingerprint.codec, SourceCodec::None, "{:?}", fingerprint.scores);
+        assert_eq!(fingerprint.bitrate_class, BitrateClass::Unknown);
+    }
+}
@@ -0,0 +1,271 @@
+// Constant-Q transform: fixed bins per octave, output shaped like the STFT
+// Brown & Puckette's sparse spectral kernel, built once for the top octave. Each lower
+// octave reuses the same kernel on a copy of the signal decimated by two (the bins halve
+// and so does the sample rate), as in Schörkhuber & Klapuri. Frames are centre-aligned
+// across octaves, so row t of every octave describes the same instant.
+use crate::fft::{fft_with_cache, TwiddleCache};
+use crate::utils::Complex;
+
+// Kernel values below this fraction of each kernel's peak are dropped (Brown & Puckette)
+const SPARSITY_THRESHOLD: f32 = 0.0054;
+// Decimation filter length; the 0.45-0.55 Nyquist transition of a Blackman sinc this long
+// keeps aliases under ~-70 dB
+const DECIMATION_TAPS: usize = 111;
+// Highest bin as a fraction of the sample rate, so every octave sits in the
+// decimator's passband
+const MAX_RELATIVE_FREQ: f32 = 0.45;
+
+pub struct ConstantQ {
+    sample_rate: f32,
+    bins_per_octave: usize,
+    bin_hz: Vec<f32>,
+    num_octaves: usize,
+    hop_size: usize,
+    fft_size: usize,
+    twiddle_cache: TwiddleCache,
+    // Sparse (bin, conj(K) / fft_size) per top-octave bin, lowest first
+    kernel: Vec<Vec<(usize, Complex)>>,
+    decimation_filter: Vec<f32>,
+}
+
+impl ConstantQ {
+    // Bins at min_hz * 2^(k / bins_per_octave) up to max_hz (capped at 0.45 fs)
+    pub fn new(sample_rate: f32, min_hz: f32, max_hz: f32, bins_per_octave: usize) -> ConstantQ {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(bins_per_octave >= 1, "bins_per_octave must be >= 1");
+        let max_hz = max_hz.min(MAX_RELATIVE_FREQ * sample_rate);
+        assert!(min_hz > 0.0 && max_hz > min_hz, "need 0 < min_hz < max_hz <= 0.45 * sample_rate");
+
+        let num_bins = (bins_per_octave as f64 * (max_hz as f64 / min_hz as f64).log2()).floor() as usize + 1;
+        let bin_hz: Vec<f32> = (0..num_bins)
+            .map(|k| (min_hz as f64 * 2f64.powf(k as f64 / bins_per_octave as f64)) as f32)
+            .collect();
+        let num_octaves = num_bins.div_ceil(bins_per_octave);
+
+        let top_octave = &bin_hz[num_bins.saturating_sub(bins_per_octave)..];
+        let q = 1.0 / (2f64.powf(1.0 / bins_per_octave as f64) - 1.0);
+        let longest = (q * sample_rate as f64 / top_octave[0] as f64).ceil() as usize;
+        let fft_size = longest.next_power_of_two();
+        let twiddle_cache = TwiddleCache::new(fft_size);
+        let kernel = top_octave
+            .iter()
+            .map(|&f| spectral_kernel(f as f64, q, sample_rate as f64, fft_size, &twiddle_cache))
+            .collect();
+
+        ConstantQ {
+            sample_rate,
+            bins_per_octave,
+            bin_hz,
+            num_octaves,
+            hop_size: 1 << (num_octaves - 1),
+            fft_size,
+            twiddle_cache,
+            kernel,
+            decimation_filter: halfband_lowpass(DECIMATION_TAPS),
+        }
+    }
+
+    // Hop in samples; rounded up to a multiple of 2^(octaves - 1) so every octave
+    // lands on whole samples after decimation
+    pub fn with_hop_size(mut self, hop_size: usize) -> Self {
+        let step = 1 << (self.num_octaves - 1);
+        self.hop_size = hop_size.max(1).div_ceil(step) * step;
+        self
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.bin_hz.len()
+    }
+
+    pub fn bins_per_octave(&self) -> usize {
+        self.bins_per_octave
+    }
+
+    pub fn bin_frequencies(&self) -> &[f32] {
+        &self.bin_hz
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    // (data, num_windows, num_bins) like `process_windows`: frame t is centred on sample
+    // t * hop, bins run from min_hz upwards, and a sine of amplitude A reads A in its bin
+    pub fn process(&self, audio_data: &[f32]) -> (Vec<f32>, usize, usize) {
+        let num_bins = self.num_bins();
+        let num_windows = audio_data.len().div_ceil(self.hop_size);
+        let mut data = vec![0.0f32; num_windows * num_bins];
+        let mut buffer = vec![Complex::new(0.0, 0.0); self.fft_size];
+
+        let mut signal = audio_data.to_vec();
+        for octave in 0..self.num_octaves {
+            if octave > 0 {
+                signal = self.decimate(&signal);
+            }
+            let hop = self.hop_size >> octave;
+            // Top bin of this octave; the kernel's last entry maps onto it
+            let top = num_bins - 1 - octave * self.bins_per_octave;
+            for t in 0..num_windows {
+                let start = (t * hop) as isize - (self.fft_size / 2) as isize;
+                for (i, slot) in buffer.iter_mut().enumerate() {
+                    let index = start + i as isize;
+                    let sample = if index >= 0 && (index as usize) < signal.len() {
+                        signal[index as usize]
+                    } else {
+                        0.0
+                    };
+                    *slot = Complex::new(sample, 0.0);
+                }
+                fft_with_cache(&mut buffer, &self.twiddle_cache);
+
+                for (j, kernel) in self.kernel.iter().enumerate().rev() {
+                    let offset = self.kernel.len() - 1 - j;
+                    if offset > top {
+                        break;
+                    }
+                    let (mut re, mut im) = (0.0f32, 0.0f32);
+                    for &(k, c) in kernel {
+                        let x = buffer[k];
+                        re += x.real * c.real - x.imag * c.imag;
+                        im += x.real * c.imag + x.imag * c.real;
+                    }
+                    data[t * num_bins + top - offset] = re.hypot(im);
+                }
+            }
+        }
+        (data, num_windows, num_bins)
+    }
+
+    // Zero-phase lowpass at half the current Nyquist, then keep every other sample
+    fn decimate(&self, signal: &[f32]) -> Vec<f32> {
+        let half = (self.decimation_filter.len() / 2) as isize;
+        (0..signal.len().div_ceil(2))
+            .map(|m| {
+                let centre = (2 * m) as isize;
+                self.decimation_filter
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, h)| {
+                        let index = centre + i as isize - half;
+                        (index >= 0 && (index as usize) < signal.len()).then(|| h * signal[index as usize])
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+// FFT of a Hann-windowed complex sinusoid of Q cycles, centred in the frame, scaled so a
+// sine of amplitude A reads A; stored conjugated and divided by fft_size, so a frame's
+// coefficient is sum(X[k] * kernel[k])
+fn spectral_kernel(freq: f64, q: f64, sample_rate: f64, fft_size: usize, twiddle_cache: &TwiddleCache) -> Vec<(usize, Complex)> {
+    let length = ((q * sample_rate / freq).round() as usize).clamp(1, fft_size);
+    let window: Vec<f64> = (0..length)
+        .map(|n| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * (n as f64 + 0.5) / length as f64).cos())
+        .collect();
+    let window_sum: f64 = window.iter().sum();
+    let start = (fft_size - length) / 2;
+
+    let mut atom = vec![Complex::new(0.0, 0.0); fft_size];
+    for (n, w) in window.iter().enumerate() {
+        // Phase measured from the frame centre
+        let phase = 2.0 * std::f64::consts::PI * freq * (start as f64 + n as f64 - (fft_size / 2) as f64) / sample_rate;
+        let scale = 2.0 * w / window_sum;
+        atom[start + n] = Complex::new((scale * phase.cos()) as f32, (scale * phase.sin()) as f32);
+    }
+    fft_with_cache(&mut atom, twiddle_cache);
+
+    let peak = atom.iter().map(|c| c.magnitude()).fold(0.0f32, f32::max);
+    atom.iter()
+        .enumerate()
+        .filter(|(_, c)| c.magnitude() >= SPARSITY_THRESHOLD * peak)
+        .map(|(k, c)| (k, Complex::new(c.real / fft_size as f32, -c.imag / fft_size as f32)))
+        .collect()
+}
+
+// Blackman-windowed sinc with its cutoff at a quarter of the sample rate
+fn halfband_lowpass(taps: usize) -> Vec<f32> {
+    let centre = (taps / 2) as f64;
+    let pi = std::f64::consts::PI;
+    (0..taps)
+        .map(|i| {
+            let x = i as f64 - centre;
+            let sinc = if x == 0.0 { 0.5 } else { (0.5 * pi * x).sin() / (pi * x) };
+            let phase = 2.0 * pi * i as f64 / (taps - 1) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::sine;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    fn column(data: &[f32], num_bins: usize, t: usize) -> &[f32] {
+        &data[t * num_bins..(t + 1) * num_bins]
+    }
+
+    #[test]
+    fn test_bin_layout() {
+        let cqt = ConstantQ::new(SAMPLE_RATE, 55.0, 14_080.0, 12);
+        // eight octaves of semitones, both ends included
+        assert_eq!(cqt.num_bins(), 97);
+        assert!((cqt.bin_frequencies()[12] - 110.0).abs() < 1e-3);
+        assert!((cqt.bin_frequencies()[96] - 14_080.0).abs() < 0.1);
+        assert_eq!(cqt.hop_size() % (1 << 8), 0);
+        // max_hz is capped at 0.45 fs
+        assert!(*ConstantQ::new(SAMPLE_RATE, 100.0, 22_000.0, 24).bin_frequencies().last().unwrap() <= 0.45 * SAMPLE_RATE);
+        // 97 bins are nine octaves (the lowest holds only 55 Hz), so hops step by 256
+        assert_eq!(ConstantQ::new(SAMPLE_RATE, 55.0, 14_080.0, 12).with_hop_size(300).hop_size(), 512);
+    }
+
+    #[test]
+    fn test_tones_read_their_amplitude_in_every_octave() {
+        let cqt = ConstantQ::new(SAMPLE_RATE, 55.0, 14_080.0, 12).with_hop_size(512);
+        // A2, A4 and A7 go through 7, 5 and 2 decimations before their octave is analysed
+        for (bin, amplitude) in [(12, 0.5), (36, 0.25), (84, 0.1)] {
+            let freq = cqt.bin_frequencies()[bin];
+            let (data, num_windows, num_bins) = cqt.process(&sine(freq, amplitude, 1 << 16, SAMPLE_RATE));
+            let frame = column(&data, num_bins, num_windows / 2);
+            assert!((frame[bin] / amplitude - 1.0).abs() < 0.03, "bin {}: {}", bin, frame[bin]);
+            // Hann atoms: a semitone away is down, three away is gone
+            assert!(frame[bin + 3] < 0.03 * amplitude && frame[bin - 3] < 0.03 * amplitude, "bin {}", bin);
+            let loudest = frame.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+            assert_eq!(loudest, bin);
+        }
+    }
+
+    #[test]
+    fn test_decimation_does_not_alias() {
+        // A tone near the top must stay out of the decimated octaves
+        let cqt = ConstantQ::new(SAMPLE_RATE, 55.0, 14_080.0, 12).with_hop_size(512);
+        let (data, num_windows, num_bins) = cqt.process(&sine(13_000.0, 1.0, 1 << 15, SAMPLE_RATE));
+        let frame = column(&data, num_bins, num_windows / 2);
+        let below = frame[..num_bins - 24].iter().cloned().fold(0.0f32, f32::max);
+        assert!(20.0 * below.log10() < -60.0, "{}", 20.0 * below.log10());
+    }
+
+    #[test]
+    fn test_frames_line_up_in_time() {
+        // Low and high tone both start halfway through
+        let len = 1 << 16;
+        let cqt = ConstantQ::new(SAMPLE_RATE, 55.0, 14_080.0, 12).with_hop_size(1024);
+        let mut signal = vec![0.0f32; len / 2];
+        let both: Vec<f32> = sine(110.0, 0.5, len / 2, SAMPLE_RATE).iter().zip(sine(7040.0, 0.5, len / 2, SAMPLE_RATE)).map(|(a, b)| a + b).collect();
+        signal.extend(both);
+        let (data, num_windows, num_bins) = cqt.process(&signal);
+        assert_eq!(num_windows, len / 1024);
+        // Level crosses half its final value within a few frames of the onset in both
+        let onset = |bin: usize| (0..num_windows).find(|&t| column(&data, num_bins, t)[bin] > 0.25).unwrap();
+        let (low, high) = (onset(12), onset(84));
+        assert!(low.abs_diff(num_windows / 2) <= 2 && high.abs_diff(num_windows / 2) <= 1, "{} {}", low, high);
+    }
+}
@@ -0,0 +1,20 @@
+// Shared dB conversions for the analysers
+// Zero (digital silence) and anything below the floor read FLOOR_DB rather than -inf, so
+// results stay finite for serialisation and sorting.
+pub(crate) const FLOOR_DB: f32 = -200.0;
+
+pub(crate) fn amplitude_to_db(amplitude: f32) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
+
+pub(crate) fn power_to_db(power: f32) -> f32 {
+    if power > 0.0 {
+        (10.0 * power.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
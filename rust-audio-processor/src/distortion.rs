@@ -0,0 +1,284 @@
+// Test-tone distortion measurement: THD, THD+N, SINAD, SNR and ENOB
+// Power-averages Blackman-Harris (or flat-top) FFT frames, finds the fundamental and
+// integrates each harmonic's main lobe up to Nyquist. Everything that is neither DC, the
+// fundamental nor a harmonic is counted as noise. An optional weighting (A, 468, ...)
+// applies to that noise and distortion residual only, so THD+N, SINAD and SNR come out
+// weighted while the fundamental and harmonic levels stay flat.
+use crate::db::{amplitude_to_db, power_to_db};
+use crate::fft::{fft_with_cache, TwiddleCache};
+use crate::peaks::{interpolate, PeakInterpolation};
+use crate::utils::Complex;
+use crate::weighting::Weighting;
+use crate::window::{generate_window, WindowKind};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HarmonicLevel {
+    // 2 = second harmonic, ...
+    pub order: usize,
+    pub frequency_hz: f32,
+    pub amplitude: f32,
+    // Relative to the fundamental
+    pub level_dbc: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DistortionAnalysis {
+    pub fundamental_hz: f32,
+    // Peak amplitude of the fundamental (1.0 = full scale) and in dBFS
+    pub fundamental_amplitude: f32,
+    pub fundamental_dbfs: f32,
+    pub harmonics: Vec<HarmonicLevel>,
+    // Ratios relative to the fundamental, as a percentage and in dB
+    pub thd_percent: f32,
+    pub thd_db: f32,
+    pub thd_n_percent: f32,
+    pub thd_n_db: f32,
+    // (signal + noise + distortion) over (noise + distortion)
+    pub sinad_db: f32,
+    // Fundamental over noise, harmonics excluded
+    pub snr_db: f32,
+    // (SINAD - 1.76) / 6.02
+    pub enob_bits: f32,
+    pub frames_averaged: usize,
+}
+
+pub struct DistortionAnalyzer {
+    sample_rate: f32,
+    fft_size: usize,
+    window_kind: WindowKind,
+    max_harmonics: usize,
+    weighting: Weighting,
+}
+
+impl DistortionAnalyzer {
+    pub fn new(sample_rate: f32, fft_size: usize) -> DistortionAnalyzer {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        DistortionAnalyzer {
+            sample_rate,
+            fft_size,
+            window_kind: WindowKind::BlackmanHarris,
+            max_harmonics: usize::MAX,
+            weighting: Weighting::Z,
+        }
+    }
+
+    pub fn with_window(mut self, window_kind: WindowKind) -> Self {
+        self.window_kind = window_kind;
+        self
+    }
+
+    // Weighting for the noise and distortion residual (default Z, flat)
+    pub fn with_weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    // Highest harmonic order to include in THD (default: all below Nyquist)
+    pub fn with_max_harmonics(mut self, max_harmonics: usize) -> Self {
+        self.max_harmonics = max_harmonics.max(2);
+        self
+    }
+
+    // None when there is not a single full frame
+    pub fn analyze(&self, audio_data: &[f32]) -> Option<DistortionAnalysis> {
+        let (power, frames_averaged) = self.averaged_power(audio_data)?;
+        let half_width = self.window_kind.main_lobe_half_width();
+        let bins = power.len();
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let window = generate_window(self.window_kind, self.fft_size);
+        let window_power: f32 = window.iter().map(|w| w * w).sum();
+        // One-sided bin power to mean square: a tone of amplitude A sums to A^2 / 2
+        let to_mean_square = |p: f32| 2.0 * p / (self.fft_size as f32 * window_power);
+        let lobe = |centre: usize| {
+            let (lo, hi) = (centre.saturating_sub(half_width), (centre + half_width + 1).min(bins));
+            power[lo..hi].iter().map(|&p| p as f64).sum::<f64>() as f32
+        };
+
+        // DC and its lobe are never signal
+        let first = half_width + 1;
+        let fundamental_bin = (first..bins).max_by(|&a, &b| power[a].total_cmp(&power[b]))?;
+        if fundamental_bin + 1 >= bins {
+            return None;
+        }
+        let magnitudes: Vec<f32> = power.iter().map(|p| p.sqrt()).collect();
+        let (offset, _) = interpolate(&magnitudes, fundamental_bin, PeakInterpolation::Gaussian);
+        let fundamental_hz = (fundamental_bin as f32 + offset) * bin_hz;
+        let fundamental = to_mean_square(lobe(fundamental_bin));
+        let fundamental_amplitude = (2.0 * fundamental).sqrt();
+
+        // Each harmonic: strongest bin within +-2 of the expected position
+        let mut harmonics = Vec::new();
+        let mut claimed = vec![false; bins];
+        let mut claim = |centre: usize| {
+            let (lo, hi) = (centre.saturating_sub(half_width), (centre + half_width + 1).min(bins));
+            claimed[lo..hi].iter_mut().for_each(|c| *c = true);
+        };
+        claim(fundamental_bin);
+        let mut harmonic_power = 0.0f32;
+        for order in 2..=self.max_harmonics {
+            let expected = fundamental_hz * order as f32 / bin_hz;
+            if expected.round() as usize + half_width >= bins {
+                break;
+            }
+            let centre = expected.round() as usize;
+            let bin = (centre.saturating_sub(2)..=(centre + 2).min(bins - 1))
+                .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+                .unwrap_or(centre);
+            let level = to_mean_square(lobe(bin));
+            harmonic_power += level;
+            claim(bin);
+            harmonics.push(HarmonicLevel {
+                order,
+                frequency_hz: fundamental_hz * order as f32,
+                amplitude: (2.0 * level).sqrt(),
+                level_dbc: power_to_db(level / fundamental),
+            });
+        }
+
+        // Summed bin by bin rather than as total minus fundamental, which would cancel
+        // away everything below ~-70 dB in f32
+        let noise_weights: Vec<f64> = (0..bins)
+            .map(|k| 10f64.powf(self.weighting.gain_db(k as f64 * bin_hz as f64) / 10.0))
+            .collect();
+        let outside = |skip_harmonics: bool| {
+            let sum: f64 = (first..bins)
+                .filter(|&k| k.abs_diff(fundamental_bin) > half_width && !(skip_harmonics && claimed[k]))
+                .map(|k| power[k] as f64 * noise_weights[k])
+                .sum();
+            to_mean_square(sum as f32).max(f32::MIN_POSITIVE)
+        };
+        let noise_and_distortion = outside(false);
+        let noise = outside(true);
+        let total = fundamental + noise_and_distortion;
+
+        let thd = (harmonic_power / fundamental).sqrt();
+        let thd_n = (noise_and_distortion / fundamental).sqrt();
+        let sinad_db = power_to_db(total / noise_and_distortion);
+        Some(DistortionAnalysis {
+            fundamental_hz,
+            fundamental_amplitude,
+            fundamental_dbfs: amplitude_to_db(fundamental_amplitude),
+            harmonics,
+            thd_percent: 100.0 * thd,
+            thd_db: amplitude_to_db(thd),
+            thd_n_percent: 100.0 * thd_n,
+            thd_n_db: amplitude_to_db(thd_n),
+            sinad_db,
+            snr_db: power_to_db(fundamental / noise),
+            enob_bits: (sinad_db - 1.76) / 6.02,
+            frames_averaged,
+        })
+    }
+
+    // Mean |X|^2 over all complete non-overlapping frames, bins 0..=fft_size / 2
+    fn averaged_power(&self, audio_data: &[f32]) -> Option<(Vec<f32>, usize)> {
+        let frames = audio_data.len() / self.fft_size;
+        if frames == 0 {
+            return None;
+        }
+        let window = generate_window(self.window_kind, self.fft_size);
+        let twiddle_cache = TwiddleCache::new(self.fft_size);
+        let mut buffer = vec![Complex::new(0.0, 0.0); self.fft_size];
+        let mut power = vec![0.0f32; self.fft_size / 2 + 1];
+
+        for frame in audio_data.chunks_exact(self.fft_size) {
+            for ((slot, &s), &w) in buffer.iter_mut().zip(frame.iter()).zip(window.iter()) {
+                *slot = Complex::new(s * w, 0.0);
+            }
+            fft_with_cache(&mut buffer, &twiddle_cache);
+            for (p, c) in power.iter_mut().zip(buffer.iter()) {
+                *p += (c.real * c.real + c.imag * c.imag) / frames as f32;
+            }
+        }
+        Some((power, frames))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::FLOOR_DB;
+    use crate::test_signals::white_noise;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const FFT_SIZE: usize = 16_384;
+
+    fn tone_with_harmonics(freq: f32, parts: &[(usize, f32)], len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                // f64 phase: f32 phase error alone would sit around -80 dB
+                let phase = 2.0 * std::f64::consts::PI * freq as f64 * i as f64 / SAMPLE_RATE as f64;
+                parts.iter().map(|&(order, amp)| amp * (order as f64 * phase).sin() as f32).sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_known_harmonics() {
+        // 1% second and 0.5% third harmonic: THD = sqrt(1 + 0.25) % = 1.118 %
+        let signal = tone_with_harmonics(1000.0, &[(1, 0.5), (2, 0.005), (3, 0.0025)], FFT_SIZE * 2);
+        let analysis = DistortionAnalyzer::new(SAMPLE_RATE, FFT_SIZE).analyze(&signal).unwrap();
+
+        assert_eq!(analysis.frames_averaged, 2);
+        assert!((analysis.fundamental_hz - 1000.0).abs() < 0.5);
+        assert!((analysis.fundamental_dbfs + 6.02).abs() < 0.05, "{}", analysis.fundamental_dbfs);
+        assert!((analysis.thd_percent - 1.118).abs() < 0.01, "{}", analysis.thd_percent);
+        assert!((analysis.harmonics[0].level_dbc + 40.0).abs() < 0.1);
+        assert!((analysis.harmonics[1].level_dbc + 46.02).abs() < 0.1);
+        assert!(analysis.harmonics[2].level_dbc < -100.0);
+        assert!(analysis.harmonics.iter().all(|h| h.level_dbc >= FLOOR_DB));
+        // 23 harmonics fit under 24 kHz
+        assert_eq!(analysis.harmonics.last().unwrap().order, 23);
+        assert!(analysis.thd_n_percent >= analysis.thd_percent);
+    }
+
+    #[test]
+    fn test_noise_snr_and_flat_top() {
+        // Uniform noise of +-0.001 has a mean square of 1e-6 / 3 against 0.125 for the tone
+        let mut signal = tone_with_harmonics(1234.0, &[(1, 0.5)], FFT_SIZE * 4);
+        for (s, n) in signal.iter_mut().zip(white_noise(FFT_SIZE * 4, 0.001, 40)) {
+            *s += n;
+        }
+        let expected_snr = 10.0 * (0.125f32 / (1e-6 / 3.0)).log10();
+        for window in [WindowKind::BlackmanHarris, WindowKind::FlatTop] {
+            let analysis = DistortionAnalyzer::new(SAMPLE_RATE, FFT_SIZE).with_window(window).analyze(&signal).unwrap();
+            assert!((analysis.snr_db - expected_snr).abs() < 0.5, "{:?}: {}", window, analysis.snr_db);
+            assert!((analysis.sinad_db - expected_snr).abs() < 0.5);
+            assert!((analysis.fundamental_dbfs + 6.02).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_weighted_noise() {
+        // White noise under a 1 kHz tone: A-weighting discounts the top octave, 468 adds
+        // up to 12 dB around 6.3 kHz. The tone sits at 0 dB on both curves.
+        let mut signal = tone_with_harmonics(1000.0, &[(1, 0.5), (2, 0.005)], FFT_SIZE * 4);
+        for (s, n) in signal.iter_mut().zip(white_noise(FFT_SIZE * 4, 0.001, 41)) {
+            *s += n;
+        }
+        let analyze = |weighting| DistortionAnalyzer::new(SAMPLE_RATE, FFT_SIZE).with_weighting(weighting).analyze(&signal).unwrap();
+        let (flat, a, itu) = (analyze(Weighting::Z), analyze(Weighting::A), analyze(Weighting::Itu468));
+
+        assert!(a.snr_db > flat.snr_db + 1.0, "{} vs {}", a.snr_db, flat.snr_db);
+        assert!(itu.snr_db < flat.snr_db - 5.0, "{} vs {}", itu.snr_db, flat.snr_db);
+        assert!(itu.thd_n_percent > flat.thd_n_percent);
+        assert_eq!(a.thd_percent, flat.thd_percent);
+        assert_eq!(a.fundamental_dbfs, flat.fundamental_dbfs);
+    }
+
+    #[test]
+    fn test_16_bit_enob() {
+        // Full-scale tone, bin-centred (341 cycles per frame), quantised to 16 bits:
+        // SINAD = 6.02 * 16 + 1.76 = 98.1 dB
+        let freq = 341.0 * SAMPLE_RATE / FFT_SIZE as f32;
+        let signal: Vec<f32> = tone_with_harmonics(freq, &[(1, 0.999)], FFT_SIZE * 2)
+            .iter()
+            .map(|s| (s * 32767.0).round() / 32767.0)
+            .collect();
+        let analysis = DistortionAnalyzer::new(SAMPLE_RATE, FFT_SIZE).analyze(&signal).unwrap();
+        assert!((analysis.enob_bits - 16.0).abs() < 0.4, "{}", analysis.enob_bits);
+    }
+}
@@ -0,0 +1,312 @@
+// Dynamic range metrics for library audits: Pleasurize-style DR per channel and track,
+// peak-to-loudness ratio, crest factor over time and a histogram of short-term RMS.
+// Streams decoded blocks through the shared Framer, so it can run in the same pass as
+// a SpectrogramStream.
+use crate::db::{amplitude_to_db, FLOOR_DB};
+use crate::framing::Framer;
+use crate::loudness::{LoudnessMeter, LoudnessStream};
+use crate::multichannel::BlockDeinterleaver;
+use crate::true_peak::TruePeakMeter;
+
+// Everything is built from 100 ms frames: DR blocks are 3 s (30 frames), the crest
+// series steps in 1 s (10 frames), the RMS histogram counts single frames
+const FRAMES_PER_SECOND: usize = 10;
+const DR_BLOCK_FRAMES: usize = 30;
+const CREST_FRAMES: usize = 10;
+// DR takes the loudest 20% of the blocks
+const DR_LOUDEST_FRACTION: f32 = 0.2;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RmsHistogram {
+    // Lower edge of the first bin; quieter frames (and silence) land in it
+    pub min_db: f32,
+    pub bin_width_db: f32,
+    pub counts: Vec<usize>,
+}
+
+impl RmsHistogram {
+    fn new(min_db: f32, bin_width_db: f32) -> RmsHistogram {
+        let bins = (-min_db / bin_width_db).ceil() as usize;
+        RmsHistogram {
+            min_db,
+            bin_width_db,
+            counts: vec![0; bins],
+        }
+    }
+
+    fn add(&mut self, level_db: f32) {
+        let bin = ((level_db - self.min_db) / self.bin_width_db).floor().max(0.0) as usize;
+        let last = self.counts.len() - 1;
+        self.counts[bin.min(last)] += 1;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynamicsAnalysis {
+    // Track DR: channel DR values averaged and rounded, as the DR database shows it
+    pub dr_score: u32,
+    pub channel_dr_db: Vec<f32>,
+    pub true_peak_dbtp: f32,
+    pub integrated_lufs: Option<f32>,
+    // True peak minus integrated loudness
+    pub peak_to_loudness_db: Option<f32>,
+    // Peak over RMS of the whole programme (all channels)
+    pub crest_factor_db: f32,
+    // Peak over RMS per second, FLOOR_DB for silent seconds
+    pub crest_factor_series_db: Vec<f32>,
+    pub rms_histogram: RmsHistogram,
+}
+
+struct FrameStats {
+    sum_squares: f64,
+    peak: f32,
+    len: usize,
+}
+
+struct ChannelState {
+    framer: Framer,
+    frames: Vec<FrameStats>,
+    true_peak: TruePeakMeter,
+}
+
+pub struct DynamicsMeter {
+    channels: Vec<ChannelState>,
+    deinterleaver: BlockDeinterleaver,
+    loudness: LoudnessStream,
+}
+
+impl DynamicsMeter {
+    pub fn new(sample_rate: f32, num_channels: usize) -> DynamicsMeter {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(num_channels >= 1, "num_channels must be >= 1");
+        let frame_len = (sample_rate / FRAMES_PER_SECOND as f32).round() as usize;
+        DynamicsMeter {
+            channels: (0..num_channels)
+                .map(|_| ChannelState {
+                    framer: Framer::new(frame_len, frame_len),
+                    frames: Vec::new(),
+                    true_peak: TruePeakMeter::new(),
+                })
+                .collect(),
+            deinterleaver: BlockDeinterleaver::new(num_channels),
+            loudness: LoudnessMeter::new(sample_rate, num_channels).stream(),
+        }
+    }
+
+    // Blocks may end mid-frame; the partial frame waits for the next push
+    pub fn push(&mut self, interleaved: &[f32]) {
+        let channels = self.deinterleaver.push(interleaved);
+        let planes: Vec<&[f32]> = channels.iter().map(|c| c.as_slice()).collect();
+        self.push_channels(&planes);
+    }
+
+    pub fn push_channels(&mut self, channels: &[&[f32]]) {
+        assert_eq!(channels.len(), self.channels.len(), "channel count does not match the meter");
+        for (state, samples) in self.channels.iter_mut().zip(channels.iter()) {
+            state.true_peak.process(samples);
+            let frames = &mut state.frames;
+            state.framer.push(samples, |_, frame| frames.push(frame_stats(frame)));
+        }
+        self.loudness.push_channels(channels);
+    }
+
+    // The partial last frame still counts towards DR and the histogram
+    pub fn finish(mut self) -> DynamicsAnalysis {
+        for state in self.channels.iter_mut() {
+            if !state.framer.pending().is_empty() {
+                let tail = frame_stats(state.framer.pending());
+                state.frames.push(tail);
+            }
+            state.true_peak.process(&[0.0; 12]);
+        }
+
+        let channel_dr_db: Vec<f32> = self.channels.iter().map(|c| dr_value(&c.frames)).collect();
+        let dr_score = (channel_dr_db.iter().sum::<f32>() / channel_dr_db.len() as f32).round().max(0.0) as u32;
+
+        let true_peak = self.channels.iter().fold(0.0f32, |p, c| p.max(c.true_peak.peak()));
+        let true_peak_dbtp = amplitude_to_db(true_peak);
+        let integrated_lufs = self.loudness.finish().integrated_lufs;
+
+        let num_frames = self.channels.iter().map(|c| c.frames.len()).min().unwrap_or(0);
+        // Frame stats combined across channels: summed squares and samples, max peak
+        let combined: Vec<FrameStats> = (0..num_frames)
+            .map(|i| FrameStats {
+                sum_squares: self.channels.iter().map(|c| c.frames[i].sum_squares).sum(),
+                peak: self.channels.iter().fold(0.0f32, |p, c| p.max(c.frames[i].peak)),
+                len: self.channels.iter().map(|c| c.frames[i].len).sum(),
+            })
+            .collect();
+
+        let mut rms_histogram = RmsHistogram::new(-60.0, 1.0);
+        for state in &self.channels {
+            for frame in &state.frames {
+                rms_histogram.add(rms_db(frame.sum_squares, frame.len));
+            }
+        }
+
+        DynamicsAnalysis {
+            dr_score,
+            channel_dr_db,
+            true_peak_dbtp,
+            integrated_lufs,
+            peak_to_loudness_db: integrated_lufs.map(|lufs| true_peak_dbtp - lufs),
+            crest_factor_db: crest_db(&combined),
+            crest_factor_series_db: combined.chunks(CREST_FRAMES).map(crest_db).collect(),
+            rms_histogram,
+        }
+    }
+}
+
+fn frame_stats(frame: &[f32]) -> FrameStats {
+    FrameStats {
+        sum_squares: frame.iter().map(|&s| (s as f64) * (s as f64)).sum(),
+        peak: frame.iter().fold(0.0f32, |p, s| p.max(s.abs())),
+        len: frame.len(),
+    }
+}
+
+fn rms_db(sum_squares: f64, len: usize) -> f32 {
+    amplitude_to_db((sum_squares / len.max(1) as f64).sqrt() as f32)
+}
+
+fn crest_db(frames: &[FrameStats]) -> f32 {
+    let peak = frames.iter().fold(0.0f32, |p, f| p.max(f.peak));
+    let sum_squares: f64 = frames.iter().map(|f| f.sum_squares).sum();
+    let len: usize = frames.iter().map(|f| f.len).sum();
+    if peak == 0.0 {
+        return FLOOR_DB;
+    }
+    amplitude_to_db(peak) - rms_db(sum_squares, len)
+}
+
+// Pleasurize DR for one channel: per 3 s block, RMS scaled by sqrt(2) (so a full-scale
+// sine reads 0 dB) and the block peak; DR is the second-highest block peak over the RMS
+// of the loudest 20% of blocks
+fn dr_value(frames: &[FrameStats]) -> f32 {
+    let blocks: Vec<(f32, f32)> = frames
+        .chunks(DR_BLOCK_FRAMES)
+        .map(|block| {
+            let sum_squares: f64 = block.iter().map(|f| f.sum_squares).sum();
+            let len: usize = block.iter().map(|f| f.len).sum();
+            let rms = (2.0 * sum_squares / len.max(1) as f64).sqrt() as f32;
+            (rms, block.iter().fold(0.0f32, |p, f| p.max(f.peak)))
+        })
+        .collect();
+    if blocks.is_empty() {
+        return 0.0;
+    }
+
+    let mut rms: Vec<f32> = blocks.iter().map(|b| b.0).collect();
+    rms.sort_by(|a, b| b.total_cmp(a));
+    let loudest = ((rms.len() as f32 * DR_LOUDEST_FRACTION).round() as usize).max(1);
+    let top_rms = (rms[..loudest].iter().map(|r| r * r).sum::<f32>() / loudest as f32).sqrt();
+
+    let mut peaks: Vec<f32> = blocks.iter().map(|b| b.1).collect();
+    peaks.sort_by(|a, b| b.total_cmp(a));
+    let peak = if peaks.len() > 1 { peaks[1] } else { peaks[0] };
+
+    if top_rms <= 0.0 || peak <= 0.0 {
+        return 0.0;
+    }
+    20.0 * (peak / top_rms).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::SpectrogramStream;
+    use crate::SpectrogramProcessor;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    fn sine(amplitude: f32, seconds: f32) -> Vec<f32> {
+        (0..(seconds * SAMPLE_RATE) as usize)
+            .map(|i| amplitude * (2.0 * PI * 1000.0 * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn analyze_mono(samples: &[f32]) -> DynamicsAnalysis {
+        let mut meter = DynamicsMeter::new(SAMPLE_RATE, 1);
+        meter.push(samples);
+        meter.finish()
+    }
+
+    #[test]
+    fn test_sine_metrics() {
+        let analysis = analyze_mono(&sine(0.5, 12.0));
+        // sqrt(2) * RMS equals the peak of a sine, so DR is 0
+        assert!(analysis.channel_dr_db[0].abs() < 0.05, "{}", analysis.channel_dr_db[0]);
+        assert_eq!(analysis.dr_score, 0);
+        assert!((analysis.crest_factor_db - 3.01).abs() < 0.05);
+        assert!(analysis.crest_factor_series_db.iter().all(|c| (c - 3.01).abs() < 0.05));
+        // mono 1 kHz at -6 dBFS peak reads -9 LUFS, true peak -6 dBTP
+        assert!((analysis.peak_to_loudness_db.unwrap() - 3.01).abs() < 0.1);
+        // RMS -9.03 dBFS lands in the [-10, -9) bin
+        let bin = analysis.rms_histogram.counts.iter().position(|&c| c > 0).unwrap();
+        assert_eq!(analysis.rms_histogram.min_db + bin as f32, -10.0);
+        assert_eq!(analysis.rms_histogram.counts.iter().sum::<usize>(), 120);
+    }
+
+    #[test]
+    fn test_dr_with_transient_peaks() {
+        // -6 dBFS sine with a full-scale click in two of the four blocks: second peak 1.0
+        // over a top RMS of 0.5 gives DR 6
+        let mut samples = sine(0.5, 12.0);
+        samples[1000] = 1.0;
+        samples[(7.0 * SAMPLE_RATE) as usize] = -1.0;
+        let analysis = analyze_mono(&samples);
+        assert!((analysis.channel_dr_db[0] - 6.02).abs() < 0.05, "{}", analysis.channel_dr_db[0]);
+        assert_eq!(analysis.dr_score, 6);
+    }
+
+    #[test]
+    fn test_single_pass_with_spectrogram() {
+        // Left at -6 dBFS, right with clicks: per-channel DR differs, track DR is their mean
+        let left = sine(0.5, 9.0);
+        let mut right = sine(0.25, 9.0);
+        for i in (0..right.len()).step_by(44_100) {
+            right[i] = 1.0;
+        }
+        let interleaved: Vec<f32> = left.iter().zip(right.iter()).flat_map(|(&l, &r)| [l, r]).collect();
+        let downmix: Vec<f32> = left.iter().zip(right.iter()).map(|(l, r)| (l + r) / 2.0).collect();
+
+        let mut meter = DynamicsMeter::new(SAMPLE_RATE, 2);
+        let mut stream = SpectrogramStream::new(SpectrogramProcessor::new(1024), 0.5);
+        for (block, mono) in interleaved.chunks(2 * 4096).zip(downmix.chunks(4096)) {
+            meter.push(block);
+            stream.push(mono);
+        }
+        let analysis = meter.finish();
+        let (_, num_windows, freq_bins) = stream.finish();
+
+        let whole = {
+            let mut meter = DynamicsMeter::new(SAMPLE_RATE, 2);
+            meter.push(&interleaved);
+            meter.finish()
+        };
+        assert_eq!(analysis, whole);
+        assert!(analysis.channel_dr_db[0].abs() < 0.05);
+        assert!((analysis.channel_dr_db[1] - 12.04).abs() < 0.05, "{}", analysis.channel_dr_db[1]);
+        assert_eq!(analysis.dr_score, 6);
+        assert_eq!((num_windows, freq_bins), ((downmix.len() - 1024) / 512 + 1, 512));
+    }
+
+    #[test]
+    fn test_push_carries_partial_frames() {
+        let tone = sine(0.5, 7.0);
+        let interleaved: Vec<f32> = tone.iter().flat_map(|&s| [s, s]).collect();
+
+        let mut whole = DynamicsMeter::new(SAMPLE_RATE, 2);
+        whole.push(&interleaved);
+        let mut blocks = DynamicsMeter::new(SAMPLE_RATE, 2);
+        for block in interleaved.chunks(1023) {
+            blocks.push(block);
+        }
+        let analysis = blocks.finish();
+        assert_eq!(analysis, whole.finish());
+        assert!(analysis.channel_dr_db.iter().all(|dr| dr.abs() < 0.05), "{:?}", analysis.channel_dr_db);
+    }
+}
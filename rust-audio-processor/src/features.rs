@@ -0,0 +1,266 @@
+// Per-frame spectral descriptors computed from the magnitudes `process_window` already
+// produced (no extra FFT), plus the zero-crossing rate of the time-domain frame.
+// Rolloff and flatness over time are the quickest numerical hint of a band-limited file.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::amplitude_to_db;
+
+// Number of values in `SpectralFeatures::to_array`
+pub const NUM_FEATURES: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectralFeatures {
+    // Magnitude-weighted mean frequency and the standard deviation around it
+    pub centroid_hz: f32,
+    pub spread_hz: f32,
+    // Frequencies below which 85% / 95% of the frame energy lies
+    pub rolloff_85_hz: f32,
+    pub rolloff_95_hz: f32,
+    // Geometric over arithmetic mean of the power spectrum: ~1 for noise, ~0 for tones
+    pub flatness: f32,
+    // L2 norm of the magnitude increases since the previous frame (0 for the first)
+    pub flux: f32,
+    // Peak magnitude over mean magnitude
+    pub crest: f32,
+    // Least-squares slope of the dB spectrum
+    pub slope_db_per_khz: f32,
+    // Highest frequency within 60 dB of the frame peak
+    pub bandwidth_hz: f32,
+    // Sign changes per sample in the time-domain frame
+    pub zero_crossing_rate: f32,
+    // Frame RMS in dBFS, so silent frames can be told apart from real ones
+    pub rms_db: f32,
+}
+
+impl SpectralFeatures {
+    // Fixed order for flat transport (WASM): the struct field order
+    pub fn to_array(&self) -> [f32; NUM_FEATURES] {
+        [
+            self.centroid_hz,
+            self.spread_hz,
+            self.rolloff_85_hz,
+            self.rolloff_95_hz,
+            self.flatness,
+            self.flux,
+            self.crest,
+            self.slope_db_per_khz,
+            self.bandwidth_hz,
+            self.zero_crossing_rate,
+            self.rms_db,
+        ]
+    }
+}
+
+// Keeps the previous frame for flux; one extractor per stream of frames
+pub struct FeatureExtractor {
+    sample_rate: f32,
+    previous: Vec<f32>,
+}
+
+impl FeatureExtractor {
+    pub fn new(sample_rate: f32) -> FeatureExtractor {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        FeatureExtractor {
+            sample_rate,
+            previous: Vec::new(),
+        }
+    }
+
+    // `magnitudes` are the fft_size / 2 bins from `process_window`, `frame` the samples it saw
+    pub fn extract(&mut self, magnitudes: &[f32], frame: &[f32]) -> SpectralFeatures {
+        let bin_hz = self.sample_rate / (2 * magnitudes.len()).max(1) as f32;
+        let freq = |k: usize| k as f32 * bin_hz;
+
+        let flux = if self.previous.len() == magnitudes.len() {
+            magnitudes
+                .iter()
+                .zip(self.previous.iter())
+                .map(|(m, p)| (m - p).max(0.0).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        } else {
+            0.0
+        };
+        self.previous.clear();
+        self.previous.extend_from_slice(magnitudes);
+
+        let zero_crossing_rate = if frame.len() > 1 {
+            frame.windows(2).filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0)).count() as f32 / (frame.len() - 1) as f32
+        } else {
+            0.0
+        };
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len().max(1) as f32).sqrt();
+        let rms_db = amplitude_to_db(rms);
+
+        let magnitude_sum: f32 = magnitudes.iter().sum();
+        if magnitude_sum <= 0.0 {
+            return SpectralFeatures {
+                flux,
+                zero_crossing_rate,
+                rms_db,
+                ..SpectralFeatures::default()
+            };
+        }
+
+        let centroid_hz = magnitudes.iter().enumerate().map(|(k, m)| freq(k) * m).sum::<f32>() / magnitude_sum;
+        let spread_hz = (magnitudes
+            .iter()
+            .enumerate()
+            .map(|(k, m)| (freq(k) - centroid_hz).powi(2) * m)
+            .sum::<f32>()
+            / magnitude_sum)
+            .sqrt();
+
+        let power: Vec<f32> = magnitudes.iter().map(|m| m * m).collect();
+        let total_power: f32 = power.iter().sum();
+        let rolloff = |fraction: f32| {
+            let target = fraction * total_power;
+            let mut cumulative = 0.0;
+            for (k, p) in power.iter().enumerate() {
+                cumulative += p;
+                if cumulative >= target {
+                    return freq(k);
+                }
+            }
+            freq(power.len() - 1)
+        };
+
+        // Floor keeps log() finite; it sits far below any 32-bit float signal
+        let log_mean = power.iter().map(|p| (p + 1e-20).ln()).sum::<f32>() / power.len() as f32;
+        let flatness = (log_mean.exp() / (total_power / power.len() as f32)).min(1.0);
+
+        let peak = magnitudes.iter().cloned().fold(0.0f32, f32::max);
+        let crest = peak / (magnitude_sum / magnitudes.len() as f32);
+
+        let threshold = peak * 1e-3;
+        let bandwidth_hz = magnitudes.iter().rposition(|&m| m >= threshold).map(freq).unwrap_or(0.0);
+
+        SpectralFeatures {
+            centroid_hz,
+            spread_hz,
+            rolloff_85_hz: rolloff(0.85),
+            rolloff_95_hz: rolloff(0.95),
+            flatness,
+            flux,
+            crest,
+            slope_db_per_khz: db_slope_per_khz(magnitudes, bin_hz),
+            bandwidth_hz,
+            zero_crossing_rate,
+            rms_db,
+        }
+    }
+}
+
+fn db_slope_per_khz(magnitudes: &[f32], bin_hz: f32) -> f32 {
+    let n = magnitudes.len() as f32;
+    let khz = |k: usize| k as f32 * bin_hz / 1000.0;
+    let db: Vec<f32> = magnitudes.iter().map(|m| 20.0 * (m + 1e-10).log10()).collect();
+    let mean_x = (0..magnitudes.len()).map(khz).sum::<f32>() / n;
+    let mean_y = db.iter().sum::<f32>() / n;
+    let (mut covariance, mut variance) = (0.0f32, 0.0f32);
+    for (k, y) in db.iter().enumerate() {
+        let dx = khz(k) - mean_x;
+        covariance += dx * (y - mean_y);
+        variance += dx * dx;
+    }
+    if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    }
+}
+
+impl SpectrogramProcessor {
+    // `process_windows` plus one SpectralFeatures per emitted window. Features are taken
+    // from the full-resolution magnitudes before the frequency stride is applied.
+    pub fn process_windows_with_features(
+        &mut self,
+        audio_data: &[f32],
+        overlap: f32,
+        sample_rate: f32,
+    ) -> (Vec<f32>, usize, usize, Vec<SpectralFeatures>) {
+        let fft_size = self.fft_size();
+        let freq_stride = self.freq_stride();
+        let reduced_bins = (fft_size / 2).div_ceil(freq_stride);
+        let mut extractor = FeatureExtractor::new(sample_rate);
+
+        let starts = self.window_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+        let mut data = Vec::with_capacity(num_windows * reduced_bins);
+        let mut features = Vec::with_capacity(num_windows);
+        for start in starts {
+            let frame = &audio_data[start..start + fft_size];
+            let magnitudes = self.process_window(frame);
+            features.push(extractor.extract(&magnitudes, frame));
+            data.extend(magnitudes.into_iter().step_by(freq_stride));
+        }
+
+        (data, num_windows, reduced_bins, features)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::FLOOR_DB;
+    use crate::test_signals::{brickwall_lowpass, sine, white_noise};
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    #[test]
+    fn test_pure_tone_features() {
+        let signal = sine(1000.0, 0.5, 8192, SAMPLE_RATE);
+        let (_, num_windows, _, features) = SpectrogramProcessor::new(2048).process_windows_with_features(&signal, 0.5, SAMPLE_RATE);
+        assert_eq!(features.len(), num_windows);
+
+        let f = &features[1];
+        assert!((f.centroid_hz - 1000.0).abs() < 30.0, "{}", f.centroid_hz);
+        assert!((f.rolloff_85_hz - 1000.0).abs() < 30.0);
+        assert!(f.flatness < 0.01);
+        assert!(f.crest > 50.0);
+        // 2 crossings per cycle
+        assert!((f.zero_crossing_rate - 2.0 * 1000.0 / SAMPLE_RATE).abs() < 0.002);
+        // steady tone: nothing rises between frames
+        assert!(f.flux < 1e-2 * f.crest);
+    }
+
+    #[test]
+    fn test_noise_and_lowpass() {
+        let noise = white_noise(1 << 15, 0.5, 21);
+        let lowpassed = brickwall_lowpass(&noise, 16_000.0, SAMPLE_RATE);
+        let mut processor = SpectrogramProcessor::new(2048);
+        let (_, _, _, full) = processor.process_windows_with_features(&noise, 0.5, SAMPLE_RATE);
+        let (_, _, _, cut) = processor.process_windows_with_features(&lowpassed, 0.5, SAMPLE_RATE);
+
+        let mean = |fs: &[SpectralFeatures], get: fn(&SpectralFeatures) -> f32| fs.iter().map(get).sum::<f32>() / fs.len() as f32;
+        // white noise: centroid at a quarter of the sample rate, flat, 95% rolloff near 21 kHz
+        assert!((mean(&full, |f| f.centroid_hz) - 11_025.0).abs() < 300.0);
+        assert!(mean(&full, |f| f.flatness) > 0.4);
+        assert!(mean(&full, |f| f.rolloff_95_hz) > 20_000.0);
+        assert!(mean(&full, |f| f.slope_db_per_khz).abs() < 0.1);
+        // 16 kHz lowpass pulls rolloff and bandwidth under the cutoff and tilts the slope
+        assert!(mean(&cut, |f| f.rolloff_95_hz) < 16_000.0);
+        assert!(mean(&cut, |f| f.bandwidth_hz) < 16_500.0);
+        assert!(mean(&cut, |f| f.slope_db_per_khz) < -1.0);
+        assert!(mean(&cut, |f| f.flatness) < mean(&full, |f| f.flatness));
+    }
+
+    #[test]
+    fn test_flux_marks_onset() {
+        let mut signal = vec![0.0f32; 4096];
+        signal.extend(sine(3000.0, 0.5, 4096, SAMPLE_RATE));
+        let (_, _, _, features) = SpectrogramProcessor::new(1024).process_windows_with_features(&signal, 0.0, SAMPLE_RATE);
+        let onset = features.iter().enumerate().max_by(|a, b| a.1.flux.total_cmp(&b.1.flux)).unwrap().0;
+        assert_eq!(onset, 4);
+        assert!(features[0].rms_db <= FLOOR_DB);
+    }
+
+    #[test]
+    fn test_strides_match_process_windows() {
+        let signal = white_noise(10_000, 0.5, 2);
+        let mut processor = SpectrogramProcessor::new(512).with_strides(3, 2);
+        let (data, num_windows, freq_bins, features) = processor.process_windows_with_features(&signal, 0.5, SAMPLE_RATE);
+        assert_eq!((data, num_windows, freq_bins), processor.process_windows(&signal, 0.5));
+        assert_eq!(features.len(), num_windows);
+    }
+}
@@ -1,7 +1,7 @@
 // Rust implementation of FFT -> Cooley-Tukey Radix 2 iteration
 use crate::utils::{bit_reverse, Complex, generate_twiddle_factor, butterfly_operation};
 
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
 use core::arch::wasm32::{f32x4_add, f32x4_mul, f32x4_sub, i32x4_shuffle, v128, v128_load, v128_store};
 
 #[derive(Debug, Clone)]
@@ -10,7 +10,7 @@ pub struct TwiddleCache {
     stages: Vec<Vec<Complex>>,
 }
 
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
 #[inline]
 unsafe fn butterfly_pair_simd(
     input: *mut Complex,
@@ -99,7 +99,7 @@ pub fn fft_with_cache(input: &mut [Complex], cache: &TwiddleCache) {
         let twiddles = cache.stage_twiddles(stage);
 
         for start in (0..n).step_by(len) {
-            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
             {
                 let mut k = 0;
                 let ptr = input.as_mut_ptr();
@@ -122,12 +122,12 @@ pub fn fft_with_cache(input: &mut [Complex], cache: &TwiddleCache) {
                 }
             }
 
-            #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
-            for k in 0..half {
+            #[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+            for (k, &twiddle) in twiddles.iter().enumerate().take(half) {
                 let i = start + k;
                 let j = i + half;
 
-                let (upper, lower) = butterfly_operation(input[i], input[j], twiddles[k]);
+                let (upper, lower) = butterfly_operation(input[i], input[j], twiddle);
                 input[i] = upper;
                 input[j] = lower;
             }
@@ -144,6 +144,11 @@ pub fn fft(input: &mut [Complex]) {
 }
 
 pub fn ifft(input: &mut [Complex]) {
+    let cache = TwiddleCache::new(input.len());
+    ifft_with_cache(input, &cache);
+}
+
+pub fn ifft_with_cache(input: &mut [Complex], cache: &TwiddleCache) {
     let n = input.len();
     // Conjugate All Inputs
     for c in input.iter_mut() {
@@ -151,7 +156,7 @@ pub fn ifft(input: &mut [Complex]) {
     }
 
     // Forward fft
-    fft(input);
+    fft_with_cache(input, cache);
 
     // Conjugate Again and Scale
     for c in input.iter_mut() {
@@ -162,6 +167,7 @@ pub fn ifft(input: &mut [Complex]) {
 
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
     use super::*;
     use crate::utils::Complex;
@@ -0,0 +1,344 @@
+// Perceptual filterbanks (mel, Bark, ERB) and MFCCs over `process_window` magnitudes
+// Built the way librosa builds `filters.mel`: triangles in Hz between points spaced evenly on
+// the perceptual scale, applied to power (|X|^2). MFCCs are an orthonormal DCT-II of
+// 10*log10(band power). Unlike librosa.feature.mfcc there is no top_db clipping, which
+// depends on the loudest frame of the whole file and so can't be done per frame.
+use crate::audio_processor::SpectrogramProcessor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrequencyScale {
+    // 2595 * log10(1 + f / 700)
+    MelHtk,
+    // Slaney's Auditory Toolbox: linear to 1 kHz, log above (librosa's default)
+    MelSlaney,
+    // Traunmüller: 26.81 f / (1960 + f) - 0.53
+    Bark,
+    // Glasberg & Moore ERB-rate: 21.4 * log10(1 + 0.00437 f)
+    Erb,
+}
+
+// Slaney mel: 3 mels per 200 Hz up to 1 kHz, then 27 mels per log(6.4)
+const SLANEY_HZ_PER_MEL: f64 = 200.0 / 3.0;
+const SLANEY_BREAK_MEL: f64 = 15.0;
+const SLANEY_LOG_STEP: f64 = 0.068_751_777_420_949_12; // ln(6.4) / 27
+
+impl FrequencyScale {
+    pub fn hz_to_scale(&self, hz: f64) -> f64 {
+        match self {
+            FrequencyScale::MelHtk => 2595.0 * (1.0 + hz / 700.0).log10(),
+            FrequencyScale::MelSlaney => {
+                if hz < 1000.0 {
+                    hz / SLANEY_HZ_PER_MEL
+                } else {
+                    SLANEY_BREAK_MEL + (hz / 1000.0).ln() / SLANEY_LOG_STEP
+                }
+            }
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+            FrequencyScale::Erb => 21.4 * (1.0 + 0.00437 * hz).log10(),
+        }
+    }
+
+    pub fn scale_to_hz(&self, value: f64) -> f64 {
+        match self {
+            FrequencyScale::MelHtk => 700.0 * (10f64.powf(value / 2595.0) - 1.0),
+            FrequencyScale::MelSlaney => {
+                if value < SLANEY_BREAK_MEL {
+                    value * SLANEY_HZ_PER_MEL
+                } else {
+                    1000.0 * ((value - SLANEY_BREAK_MEL) * SLANEY_LOG_STEP).exp()
+                }
+            }
+            FrequencyScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            FrequencyScale::Erb => (10f64.powf(value / 21.4) - 1.0) / 0.00437,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterNorm {
+    // Every triangle peaks at 1 (HTK)
+    Peak,
+    // Every triangle has the same area: 2 / (upper - lower) Hz (librosa norm="slaney")
+    Area,
+}
+
+struct Filter {
+    start_bin: usize,
+    weights: Vec<f32>,
+}
+
+pub struct Filterbank {
+    scale: FrequencyScale,
+    num_filters: usize,
+    sample_rate: f32,
+    fft_size: usize,
+    min_hz: f32,
+    max_hz: f32,
+    norm: FilterNorm,
+    centres_hz: Vec<f32>,
+    filters: Vec<Filter>,
+}
+
+impl Filterbank {
+    // `num_filters` triangles from 0 Hz to Nyquist with area normalisation
+    pub fn new(scale: FrequencyScale, num_filters: usize, sample_rate: f32, fft_size: usize) -> Filterbank {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        assert!(num_filters >= 1, "num_filters must be >= 1");
+        let mut filterbank = Filterbank {
+            scale,
+            num_filters,
+            sample_rate,
+            fft_size,
+            min_hz: 0.0,
+            max_hz: sample_rate / 2.0,
+            norm: FilterNorm::Area,
+            centres_hz: Vec::new(),
+            filters: Vec::new(),
+        };
+        filterbank.configure();
+        filterbank
+    }
+
+    pub fn with_range(mut self, min_hz: f32, max_hz: f32) -> Self {
+        assert!(min_hz >= 0.0 && max_hz > min_hz, "need 0 <= min_hz < max_hz");
+        self.min_hz = min_hz;
+        self.max_hz = max_hz.min(self.sample_rate / 2.0);
+        self.configure();
+        self
+    }
+
+    pub fn with_norm(mut self, norm: FilterNorm) -> Self {
+        self.norm = norm;
+        self.configure();
+        self
+    }
+
+    pub fn num_filters(&self) -> usize {
+        self.num_filters
+    }
+
+    pub fn centre_frequencies(&self) -> &[f32] {
+        &self.centres_hz
+    }
+
+    // Band powers (sum of weight * |X|^2) for one frame of fft_size / 2 magnitudes
+    pub fn apply(&self, magnitudes: &[f32]) -> Vec<f32> {
+        self.filters
+            .iter()
+            .map(|filter| {
+                magnitudes
+                    .iter()
+                    .skip(filter.start_bin)
+                    .zip(filter.weights.iter())
+                    .map(|(m, w)| w * m * m)
+                    .sum()
+            })
+            .collect()
+    }
+
+    // Weight of every filter at every bin, filters x fft_size / 2, for checking against
+    // other implementations
+    pub fn weight_matrix(&self) -> Vec<f32> {
+        let bins = self.fft_size / 2;
+        let mut matrix = vec![0.0; self.num_filters * bins];
+        for (i, filter) in self.filters.iter().enumerate() {
+            for (j, &w) in filter.weights.iter().enumerate() {
+                matrix[i * bins + filter.start_bin + j] = w;
+            }
+        }
+        matrix
+    }
+
+    fn configure(&mut self) {
+        let bins = self.fft_size / 2;
+        let bin_hz = self.sample_rate as f64 / self.fft_size as f64;
+        let (lo, hi) = (self.scale.hz_to_scale(self.min_hz as f64), self.scale.hz_to_scale(self.max_hz as f64));
+        let points: Vec<f64> = (0..self.num_filters + 2)
+            .map(|i| self.scale.scale_to_hz(lo + (hi - lo) * i as f64 / (self.num_filters + 1) as f64))
+            .collect();
+
+        self.centres_hz = points[1..=self.num_filters].iter().map(|&f| f as f32).collect();
+        self.filters = points
+            .windows(3)
+            .map(|edge| {
+                let (lower, centre, upper) = (edge[0], edge[1], edge[2]);
+                let scale = match self.norm {
+                    FilterNorm::Peak => 1.0,
+                    FilterNorm::Area => 2.0 / (upper - lower),
+                };
+                let start_bin = ((lower / bin_hz).ceil() as usize).min(bins);
+                let end_bin = ((upper / bin_hz).floor() as usize + 1).clamp(start_bin, bins);
+                let weights = (start_bin..end_bin)
+                    .map(|k| {
+                        let f = k as f64 * bin_hz;
+                        let rising = (f - lower) / (centre - lower);
+                        let falling = (upper - f) / (upper - centre);
+                        (rising.min(falling).max(0.0) * scale) as f32
+                    })
+                    .collect();
+                Filter { start_bin, weights }
+            })
+            .collect();
+    }
+}
+
+// Power below this is treated as this, so silent bands give a finite log
+const LOG_FLOOR: f32 = 1e-10;
+
+pub struct Mfcc {
+    filterbank: Filterbank,
+    num_coefficients: usize,
+    // num_coefficients x num_filters orthonormal DCT-II
+    dct: Vec<f32>,
+}
+
+impl Mfcc {
+    pub fn new(filterbank: Filterbank, num_coefficients: usize) -> Mfcc {
+        let n = filterbank.num_filters();
+        assert!(num_coefficients >= 1 && num_coefficients <= n, "need 1 <= num_coefficients <= num_filters");
+        let dct = (0..num_coefficients)
+            .flat_map(|k| {
+                let scale = if k == 0 { (1.0 / n as f64).sqrt() } else { (2.0 / n as f64).sqrt() };
+                (0..n).map(move |i| (scale * (std::f64::consts::PI * k as f64 * (2 * i + 1) as f64 / (2 * n) as f64).cos()) as f32)
+            })
+            .collect();
+        Mfcc {
+            filterbank,
+            num_coefficients,
+            dct,
+        }
+    }
+
+    pub fn num_coefficients(&self) -> usize {
+        self.num_coefficients
+    }
+
+    pub fn filterbank(&self) -> &Filterbank {
+        &self.filterbank
+    }
+
+    // One frame of fft_size / 2 magnitudes -> num_coefficients MFCCs
+    pub fn compute(&self, magnitudes: &[f32]) -> Vec<f32> {
+        let log_energies: Vec<f32> = self
+            .filterbank
+            .apply(magnitudes)
+            .iter()
+            .map(|&p| 10.0 * p.max(LOG_FLOOR).log10())
+            .collect();
+        self.coefficients_from_log_energies(&log_energies)
+    }
+
+    // DCT stage alone, for log band energies computed elsewhere
+    pub fn coefficients_from_log_energies(&self, log_energies: &[f32]) -> Vec<f32> {
+        self.dct
+            .chunks_exact(log_energies.len())
+            .map(|row| row.iter().zip(log_energies.iter()).map(|(d, e)| d * e).sum())
+            .collect()
+    }
+}
+
+impl SpectrogramProcessor {
+    // Band powers of every window: num_windows x num_filters, flattened window-major.
+    // The filterbank replaces the frequency stride; the time stride still applies.
+    pub fn process_windows_filterbank(&mut self, audio_data: &[f32], overlap: f32, filterbank: &Filterbank) -> (Vec<f32>, usize, usize) {
+        self.map_windows(audio_data, overlap, filterbank.num_filters(), |magnitudes| filterbank.apply(magnitudes))
+    }
+
+    // MFCCs of every window: num_windows x num_coefficients
+    pub fn process_windows_mfcc(&mut self, audio_data: &[f32], overlap: f32, mfcc: &Mfcc) -> (Vec<f32>, usize, usize) {
+        self.map_windows(audio_data, overlap, mfcc.num_coefficients(), |magnitudes| mfcc.compute(magnitudes))
+    }
+
+    // Run `map` over the magnitudes of every window the time stride keeps
+    pub(crate) fn map_windows<F: Fn(&[f32]) -> Vec<f32>>(&mut self, audio_data: &[f32], overlap: f32, width: usize, map: F) -> (Vec<f32>, usize, usize) {
+        let fft_size = self.fft_size();
+        let starts = self.window_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+        let mut data = Vec::with_capacity(num_windows * width);
+        for start in starts {
+            data.extend(map(&self.process_window(&audio_data[start..start + fft_size])));
+        }
+        (data, num_windows, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::white_noise;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 22_050.0;
+
+    #[test]
+    fn test_scale_conversions() {
+        let cases = [
+            (FrequencyScale::MelHtk, 1000.0, 1000.0),
+            (FrequencyScale::MelSlaney, 1000.0, 15.0),
+            (FrequencyScale::MelSlaney, 500.0, 7.5),
+            (FrequencyScale::Bark, 1000.0, 8.527),
+            (FrequencyScale::Erb, 1000.0, 15.62),
+        ];
+        for (scale, hz, expected) in cases {
+            assert!((scale.hz_to_scale(hz) - expected).abs() < 0.01 * expected, "{:?} {}", scale, scale.hz_to_scale(hz));
+            for f in [50.0, 999.0, 1001.0, 8000.0] {
+                assert!((scale.scale_to_hz(scale.hz_to_scale(f)) - f).abs() < 1e-6 * f, "{:?} {}", scale, f);
+            }
+        }
+    }
+
+    #[test]
+    fn test_peak_triangles_partition_unity() {
+        for scale in [FrequencyScale::MelHtk, FrequencyScale::MelSlaney, FrequencyScale::Bark, FrequencyScale::Erb] {
+            let bank = Filterbank::new(scale, 24, SAMPLE_RATE, 2048).with_range(300.0, 8000.0).with_norm(FilterNorm::Peak);
+            let centres = bank.centre_frequencies();
+            assert!(centres.windows(2).all(|c| c[1] > c[0]));
+            // Between the first and last centre, neighbouring triangles sum to 1
+            let matrix = bank.weight_matrix();
+            let bin_hz = SAMPLE_RATE / 2048.0;
+            let (first, last) = ((centres[0] / bin_hz).ceil() as usize, (centres[23] / bin_hz) as usize);
+            for k in first..=last {
+                let total: f32 = (0..24).map(|i| matrix[i * 1024 + k]).sum();
+                assert!((total - 1.0).abs() < 1e-4, "{:?} bin {}: {}", scale, k, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tone_excites_its_filter() {
+        let tone: Vec<f32> = (0..2048).map(|i| (2.0 * PI * 1500.0 * i as f32 / SAMPLE_RATE).sin()).collect();
+        let magnitudes = SpectrogramProcessor::new(2048).process_window(&tone);
+        let bank = Filterbank::new(FrequencyScale::MelSlaney, 40, SAMPLE_RATE, 2048);
+        let powers = bank.apply(&magnitudes);
+        let loudest = powers.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        let spacing = bank.centre_frequencies()[loudest + 1] - bank.centre_frequencies()[loudest];
+        assert!((bank.centre_frequencies()[loudest] - 1500.0).abs() < spacing);
+    }
+
+    #[test]
+    fn test_mfcc_dct() {
+        let bank = Filterbank::new(FrequencyScale::MelHtk, 26, SAMPLE_RATE, 1024);
+        let mfcc = Mfcc::new(bank, 26);
+        // Flat log energies only move c0, by sqrt(N) times the level
+        let flat = mfcc.coefficients_from_log_energies(&[-20.0; 26]);
+        assert!((flat[0] + 20.0 * 26f32.sqrt()).abs() < 1e-3);
+        assert!(flat[1..].iter().all(|c| c.abs() < 1e-3));
+        // Orthonormal: energy is preserved when all coefficients are kept
+        let ramp: Vec<f32> = (0..26).map(|i| i as f32 - 5.0).collect();
+        let coefficients = mfcc.coefficients_from_log_energies(&ramp);
+        let energy = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>();
+        assert!((energy(&coefficients) - energy(&ramp)).abs() < 1e-2 * energy(&ramp));
+
+        let noise = white_noise(8192, 0.5, 51);
+        let mut processor = SpectrogramProcessor::new(1024).with_strides(2, 1);
+        let mfcc = Mfcc::new(Filterbank::new(FrequencyScale::MelSlaney, 40, SAMPLE_RATE, 1024), 13);
+        let (data, num_windows, width) = processor.process_windows_mfcc(&noise, 0.5, &mfcc);
+        assert_eq!(width, 13);
+        assert_eq!(num_windows, 8);
+        assert_eq!(data.len(), 8 * 13);
+        assert!(data.iter().all(|c| c.is_finite()));
+    }
+}
@@ -0,0 +1,167 @@
+// Streaming framing shared by the STFT and the block-based meters
+// Decoded PCM arrives in arbitrary block sizes; a Framer turns it into fixed frames
+// so a single pass over the blocks can feed the spectrogram and the meters together.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::spectrum::{LongTermSpectrum, SpectrumAccumulator};
+
+pub struct Framer {
+    frame_size: usize,
+    hop_size: usize,
+    pending: Vec<f32>,
+    // samples still to drop when the hop is longer than the frame
+    skip: usize,
+    frames_emitted: usize,
+}
+
+impl Framer {
+    pub fn new(frame_size: usize, hop_size: usize) -> Framer {
+        assert!(frame_size >= 1, "frame_size must be >= 1");
+        assert!(hop_size >= 1, "hop_size must be >= 1");
+        Framer {
+            frame_size,
+            hop_size,
+            pending: Vec::with_capacity(frame_size * 2),
+            skip: 0,
+            frames_emitted: 0,
+        }
+    }
+
+    // Append samples and call `on_frame(frame_index, frame)` for every frame completed by them
+    pub fn push<F: FnMut(usize, &[f32])>(&mut self, samples: &[f32], mut on_frame: F) {
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.pending.extend_from_slice(&samples[skipped..]);
+
+        let mut start = 0;
+        while start + self.frame_size <= self.pending.len() {
+            on_frame(self.frames_emitted, &self.pending[start..start + self.frame_size]);
+            self.frames_emitted += 1;
+            start += self.hop_size;
+        }
+        let consumed = start.min(self.pending.len());
+        self.skip += start - consumed;
+        self.pending.drain(..consumed);
+    }
+
+    pub fn frames_emitted(&self) -> usize {
+        self.frames_emitted
+    }
+
+    // Samples received after the last complete frame started (the partial tail)
+    pub fn pending(&self) -> &[f32] {
+        &self.pending
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+}
+
+// Incremental `process_windows`: same windows, strides and output layout, fed block by block
+pub struct SpectrogramStream {
+    processor: SpectrogramProcessor,
+    framer: Framer,
+    data: Vec<f32>,
+    num_windows: usize,
+    spectrum: Option<SpectrumAccumulator>,
+}
+
+impl SpectrogramStream {
+    pub fn new(processor: SpectrogramProcessor, overlap: f32) -> SpectrogramStream {
+        let framer = Framer::new(processor.fft_size(), processor.hop_size(overlap));
+        SpectrogramStream {
+            processor,
+            framer,
+            data: Vec::new(),
+            num_windows: 0,
+            spectrum: None,
+        }
+    }
+
+    // Also accumulate a long-term spectrum from the windows the stream computes, before
+    // the frequency stride is applied. A time stride thins the average too.
+    pub fn with_long_term_spectrum(mut self, sample_rate: f32, percentiles: &[f32]) -> Self {
+        self.spectrum = Some(SpectrumAccumulator::new(sample_rate, &self.processor).with_percentiles(percentiles));
+        self
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let processor = &mut self.processor;
+        let (data, num_windows, spectrum) = (&mut self.data, &mut self.num_windows, &mut self.spectrum);
+        let (time_stride, freq_stride) = (processor.time_stride(), processor.freq_stride());
+        self.framer.push(samples, |index, frame| {
+            if index % time_stride == 0 {
+                let magnitudes = processor.process_window(frame);
+                if let Some(accumulator) = spectrum.as_mut() {
+                    accumulator.push(&magnitudes);
+                }
+                data.extend(magnitudes.into_iter().step_by(freq_stride));
+                *num_windows += 1;
+            }
+        });
+    }
+
+    pub fn num_windows(&self) -> usize {
+        self.num_windows
+    }
+
+    pub fn freq_bins(&self) -> usize {
+        (self.processor.fft_size() / 2).div_ceil(self.processor.freq_stride())
+    }
+
+    // Snapshot of the long-term spectrum so far, if the stream was built with one
+    pub fn long_term_spectrum(&self) -> Option<LongTermSpectrum> {
+        self.spectrum.as_ref().map(|accumulator| accumulator.spectrum())
+    }
+
+    // (data, num_windows, freq_bins) like `process_windows`
+    pub fn finish(self) -> (Vec<f32>, usize, usize) {
+        let freq_bins = self.freq_bins();
+        (self.data, self.num_windows, freq_bins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::white_noise;
+
+    #[test]
+    fn test_framer_matches_slicing() {
+        let signal = white_noise(1000, 1.0, 3);
+        for (frame_size, hop_size) in [(64, 16), (64, 64), (32, 50)] {
+            let mut framer = Framer::new(frame_size, hop_size);
+            let mut frames = Vec::new();
+            for block in signal.chunks(37) {
+                framer.push(block, |index, frame| frames.push((index, frame.to_vec())));
+            }
+            let expected: Vec<Vec<f32>> = (0..)
+                .map(|i| i * hop_size)
+                .take_while(|start| start + frame_size <= signal.len())
+                .map(|start| signal[start..start + frame_size].to_vec())
+                .collect();
+            assert_eq!(frames.len(), expected.len());
+            for (i, ((index, frame), want)) in frames.iter().zip(expected.iter()).enumerate() {
+                assert_eq!(*index, i);
+                assert_eq!(frame, want);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_matches_process_windows() {
+        let signal = white_noise(20_000, 0.5, 4);
+        let mut processor = SpectrogramProcessor::new(512).with_strides(2, 3);
+        let expected = processor.process_windows(&signal, 0.75);
+
+        let mut stream = SpectrogramStream::new(SpectrogramProcessor::new(512).with_strides(2, 3), 0.75);
+        for block in signal.chunks(1000) {
+            stream.push(block);
+        }
+        assert_eq!(stream.finish(), expected);
+    }
+}
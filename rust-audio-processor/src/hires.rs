@@ -0,0 +1,293 @@
+// Fake hi-res detection: upsampled and resampled material at 88.2 kHz and above
+// An upsampled file keeps the spectrum of its source rate: nothing above the old Nyquist
+// except dither, usually behind the resampler's anti-image filter edge. A bad (or missing)
+// interpolation filter instead leaves the baseband mirrored around the old Nyquist.
+// FFT bins are sample-rate agnostic, so the detector needs the real sample rate.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::{amplitude_to_db, power_to_db, FLOOR_DB};
+use crate::transcode::{find_edge, long_term_spectrum_db, smooth_db};
+use crate::utils::correlation;
+
+// (boundary Hz, source rate it implies), low to high
+const BOUNDARIES: [(f32, f32); 7] = [
+    (20_000.0, 44_100.0),
+    (22_050.0, 44_100.0),
+    (24_000.0, 48_000.0),
+    (44_100.0, 88_200.0),
+    (48_000.0, 96_000.0),
+    (88_200.0, 176_400.0),
+    (96_000.0, 192_000.0),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandEnergy {
+    pub boundary_hz: f32,
+    // Mean power from just above the boundary to Nyquist, relative to the in-band floor (dB)
+    pub level_above_db: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HiResVerdict {
+    // Content or a flat noise floor reaches the top of the band
+    Genuine,
+    Upsampled { original_sample_rate: f32 },
+    // Little ultrasonic content, but no resampler fingerprint to pin it on, or no
+    // signal at all
+    Inconclusive,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HiResAnalysis {
+    pub verdict: HiResVerdict,
+    pub sample_rate: f32,
+    // Best estimate of the rate the material was produced at
+    pub effective_sample_rate: f32,
+    // 10th percentile of the long-term spectrum between 1 and 20 kHz
+    pub noise_floor_db: f32,
+    pub bands: Vec<BandEnergy>,
+    // Steep edge (>= 30 dB) just below one of the boundaries
+    pub anti_image_filter_hz: Option<f32>,
+    // Spectrum mirrored around this frequency (old Nyquist)
+    pub imaging_hz: Option<f32>,
+    pub mirror_correlation: f32,
+}
+
+pub struct HiResDetector {
+    sample_rate: f32,
+    fft_size: usize,
+}
+
+impl HiResDetector {
+    pub fn new(sample_rate: f32, fft_size: usize) -> HiResDetector {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        HiResDetector { sample_rate, fft_size }
+    }
+
+    fn bin_hz(&self) -> f32 {
+        self.sample_rate / self.fft_size as f32
+    }
+
+    fn bin(&self, hz: f32) -> usize {
+        (hz / self.bin_hz()).round() as usize
+    }
+
+    // Run the spectrogram and analyse it (mono PCM)
+    pub fn analyze(&self, audio_data: &[f32]) -> HiResAnalysis {
+        let mut processor = SpectrogramProcessor::new(self.fft_size);
+        let (frames, _, freq_bins) = processor.process_windows(audio_data, 0.5);
+        self.analyze_spectrogram(&frames, freq_bins)
+    }
+
+    // Analyse existing `process_windows` output (freq_stride 1, same fft_size)
+    pub fn analyze_spectrogram(&self, frames: &[f32], freq_bins: usize) -> HiResAnalysis {
+        assert_eq!(freq_bins, self.fft_size / 2, "spectrogram must come from the detector's fft_size");
+        let nyquist = self.sample_rate / 2.0;
+        let smoothed = smooth_db(&long_term_spectrum_db(frames, freq_bins));
+        // Silence reads as empty everywhere, which is no evidence either way
+        let silent = smoothed.iter().all(|&db| db <= FLOOR_DB);
+
+        let in_band_top = self.bin(20_000.0f32.min(nyquist)).min(freq_bins);
+        let mut in_band: Vec<f32> = smoothed[self.bin(1000.0).min(in_band_top)..in_band_top].to_vec();
+        in_band.sort_by(|a, b| a.total_cmp(b));
+        let noise_floor_db = in_band.get(in_band.len() / 10).cloned().unwrap_or(FLOOR_DB);
+
+        // Only rates below the file's own can be an upsampling source; at 44.1 kHz a
+        // 20 kHz encoder lowpass is just a lowpass
+        let candidates: Vec<(f32, f32)> = BOUNDARIES
+            .iter()
+            .cloned()
+            .filter(|&(boundary, rate)| boundary + 1500.0 < nyquist && rate < self.sample_rate)
+            .collect();
+
+        let bands: Vec<BandEnergy> = candidates
+            .iter()
+            .map(|&(boundary, _)| {
+                let lo = self.bin(boundary + 500.0);
+                let hi = self.bin(nyquist - 500.0).min(freq_bins);
+                BandEnergy {
+                    boundary_hz: boundary,
+                    level_above_db: power_mean_db(&smoothed[lo..hi]) - noise_floor_db,
+                }
+            })
+            .collect();
+
+        // Lowest boundary with nothing but dither above it
+        let empty_above = candidates
+            .iter()
+            .zip(bands.iter())
+            .find(|(_, band)| band.level_above_db < -30.0)
+            .map(|(&(boundary, rate), _)| (boundary, rate));
+
+        let anti_image_filter_hz = empty_above.and_then(|(boundary, _)| self.filter_edge(&smoothed, boundary));
+
+        // Images only show up around a real Nyquist, not the 20 kHz convention
+        let (mirror_correlation, imaging) = candidates
+            .iter()
+            .filter(|&&(boundary, rate)| boundary * 2.0 == rate)
+            .map(|&(boundary, rate)| (self.mirror_correlation(frames, freq_bins, boundary), (boundary, rate)))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or((0.0, None), |(corr, candidate)| (corr, Some(candidate)));
+        let imaging = imaging.filter(|_| mirror_correlation > 0.6);
+
+        let (verdict, effective_sample_rate) = match (imaging, empty_above, anti_image_filter_hz) {
+            _ if silent => (HiResVerdict::Inconclusive, self.sample_rate),
+            (Some((_, rate)), _, _) => (HiResVerdict::Upsampled { original_sample_rate: rate }, rate),
+            (None, Some((_, rate)), Some(_)) => (HiResVerdict::Upsampled { original_sample_rate: rate }, rate),
+            (None, Some((_, rate)), None) => (HiResVerdict::Inconclusive, rate),
+            (None, None, _) => (HiResVerdict::Genuine, self.sample_rate),
+        };
+
+        HiResAnalysis {
+            verdict,
+            sample_rate: self.sample_rate,
+            effective_sample_rate,
+            noise_floor_db,
+            bands,
+            anti_image_filter_hz,
+            imaging_hz: imaging.map(|(boundary, _)| boundary),
+            mirror_correlation,
+        }
+    }
+
+    // A resampler's anti-image filter: steep, deep edge between 2.5 kHz under and 500 Hz over the boundary
+    fn filter_edge(&self, smoothed: &[f32], boundary: f32) -> Option<f32> {
+        let span = self.bin(500.0).max(4);
+        let first = self.bin(boundary - 2500.0).max(span);
+        let last = self.bin(boundary + 500.0).min(smoothed.len().saturating_sub(3 * span));
+        if first >= last {
+            return None;
+        }
+        let edge = find_edge(smoothed, first, last, span, self.bin_hz());
+        (edge.drop_db >= 30.0 && edge.steepness_db_per_khz >= 40.0).then(|| edge.cutoff_bin as f32 * self.bin_hz())
+    }
+
+    // Mean per-frame correlation of the dB spectrum at boundary + d against boundary - d
+    fn mirror_correlation(&self, frames: &[f32], freq_bins: usize, boundary: f32) -> f32 {
+        let center = self.bin(boundary);
+        let reach = self.bin(boundary / 2.0).min(freq_bins.saturating_sub(center + 1));
+        let skip = self.bin(200.0).max(1);
+        if reach <= skip + 8 {
+            return 0.0;
+        }
+
+        let total = frames.len() / freq_bins;
+        let step = (total / 16).max(1);
+        let (mut sum, mut count) = (0.0f32, 0usize);
+        for frame in frames.chunks_exact(freq_bins).step_by(step).take(16) {
+            let above: Vec<f32> = (skip..reach).map(|d| amplitude_to_db(frame[center + d])).collect();
+            let below: Vec<f32> = (skip..reach).map(|d| amplitude_to_db(frame[center - d])).collect();
+            sum += correlation(&above, &below);
+            count += 1;
+        }
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f32
+        }
+    }
+}
+
+// dB of the mean power, so a little real content is not drowned by empty bins
+fn power_mean_db(levels_db: &[f32]) -> f32 {
+    if levels_db.is_empty() {
+        return FLOOR_DB;
+    }
+    let power = levels_db.iter().map(|&db| 10f64.powf(db as f64 / 10.0)).sum::<f64>() / levels_db.len() as f64;
+    power_to_db(power as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{brickwall_lowpass, edit_blocks, mix, white_noise};
+
+    const SAMPLE_RATE: f32 = 96_000.0;
+    const LEN: usize = 1 << 17;
+
+    // 24-bit TPDF-ish dither level
+    fn dither(len: usize) -> Vec<f32> {
+        white_noise(len, 1e-7, 77)
+    }
+
+    #[test]
+    fn test_genuine_hi_res_noise() {
+        let analysis = HiResDetector::new(SAMPLE_RATE, 4096).analyze(&white_noise(LEN, 0.5, 1));
+        assert_eq!(analysis.verdict, HiResVerdict::Genuine);
+        assert_eq!(analysis.effective_sample_rate, SAMPLE_RATE);
+        assert!(analysis.bands.iter().all(|b| b.level_above_db > -6.0), "{:?}", analysis.bands);
+    }
+
+    #[test]
+    fn test_upsampled_from_44k() {
+        // Resampler passband to ~21 kHz, nothing but 24-bit dither above
+        let source = brickwall_lowpass(&white_noise(LEN, 0.5, 2), 21_000.0, SAMPLE_RATE);
+        let analysis = HiResDetector::new(SAMPLE_RATE, 4096).analyze(&mix(&source, &dither(LEN)));
+
+        assert_eq!(analysis.verdict, HiResVerdict::Upsampled { original_sample_rate: 44_100.0 });
+        assert_eq!(analysis.effective_sample_rate, 44_100.0);
+        let edge = analysis.anti_image_filter_hz.unwrap();
+        assert!((edge - 21_000.0).abs() < 200.0, "edge {}", edge);
+        assert!(analysis.imaging_hz.is_none());
+    }
+
+    #[test]
+    fn test_zero_stuffed_48k_shows_imaging() {
+        // 48 kHz material upsampled by inserting zeros: the baseband mirrors around 24 kHz
+        let source = brickwall_lowpass(&white_noise(LEN / 2, 0.5, 3), 20_000.0, 48_000.0);
+        let stuffed: Vec<f32> = source.iter().flat_map(|&s| [2.0 * s, 0.0]).collect();
+        let analysis = HiResDetector::new(SAMPLE_RATE, 4096).analyze(&stuffed);
+
+        assert_eq!(analysis.imaging_hz, Some(24_000.0));
+        assert!(analysis.mirror_correlation > 0.9, "corr {}", analysis.mirror_correlation);
+        assert_eq!(analysis.verdict, HiResVerdict::Upsampled { original_sample_rate: 48_000.0 });
+    }
+
+    #[test]
+    fn test_slow_rolloff_without_edge_is_inconclusive() {
+        // Level ramps down 80 dB between 19 and 23 kHz: empty above 22.05 kHz but far too
+        // gentle (20 dB/kHz) for a resampler's anti-image filter
+        let (start, end) = (19_000.0f32, 23_000.0f32);
+        let ramped = edit_blocks(&white_noise(LEN, 0.5, 4), LEN, |_, bins| {
+            for (k, bin) in bins.iter_mut().enumerate() {
+                let hz = k as f32 * SAMPLE_RATE / LEN as f32;
+                let db = -80.0 * ((hz - start) / (end - start)).clamp(0.0, 1.0);
+                let gain = 10f32.powf(db / 20.0);
+                bin.real *= gain;
+                bin.imag *= gain;
+            }
+        });
+
+        let analysis = HiResDetector::new(SAMPLE_RATE, 4096).analyze(&mix(&ramped, &dither(LEN)));
+        assert!(analysis.anti_image_filter_hz.is_none());
+        assert_eq!(analysis.verdict, HiResVerdict::Inconclusive, "{:?}", analysis.bands);
+        assert_eq!(analysis.effective_sample_rate, 44_100.0);
+    }
+
+    #[test]
+    fn test_lowpassed_44k_is_not_upsampled() {
+        // A LAME/AAC-style lowpass in a 44.1 kHz file says nothing about upsampling
+        let len = 1 << 16;
+        let detector = HiResDetector::new(44_100.0, 4096);
+        for cutoff in [19_000.0, 19_600.0, 19_900.0, 20_000.0] {
+            let source = brickwall_lowpass(&white_noise(len, 0.5, 5), cutoff, 44_100.0);
+            let analysis = detector.analyze(&mix(&source, &dither(len)));
+            assert_eq!(analysis.verdict, HiResVerdict::Genuine, "cutoff {}", cutoff);
+            assert_eq!(analysis.effective_sample_rate, 44_100.0);
+            assert!(analysis.anti_image_filter_hz.is_none());
+        }
+    }
+
+    #[test]
+    fn test_silence_is_inconclusive() {
+        let detector = HiResDetector::new(SAMPLE_RATE, 4096);
+        for signal in [vec![0.0; 16_384], Vec::new()] {
+            let analysis = detector.analyze(&signal);
+            assert_eq!(analysis.verdict, HiResVerdict::Inconclusive);
+            assert_eq!(analysis.effective_sample_rate, SAMPLE_RATE);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// Inverse STFT by weighted overlap-add (WOLA)
+// Pairs with `SpectrogramProcessor::process_windows_complex`: same Hann window, same hop.
+use crate::fft::{ifft_with_cache, TwiddleCache};
+use crate::hann_window::generate_hann_window;
+use crate::utils::Complex;
+use std::fmt;
+
+// Sum of `window` shifted by every multiple of `hop`, over one hop period
+fn overlap_sum(window: &[f32], hop: usize, square: bool) -> Vec<f32> {
+    let mut sums = vec![0.0f32; hop];
+    for (i, &w) in window.iter().enumerate() {
+        sums[i % hop] += if square { w * w } else { w };
+    }
+    sums
+}
+
+// Relative ripple of the overlapped analysis window (0.0 means perfectly COLA)
+pub fn cola_deviation(window: &[f32], hop: usize) -> f32 {
+    assert!(hop >= 1, "hop must be >= 1");
+    let sums = overlap_sum(window, hop, false);
+    let max = sums.iter().cloned().fold(f32::MIN, f32::max);
+    let min = sums.iter().cloned().fold(f32::MAX, f32::min);
+    if max <= 0.0 {
+        return 1.0;
+    }
+    (max - min) / max
+}
+
+// Constant overlap-add: shifted windows sum to a constant (within `tolerance`)
+pub fn is_cola(window: &[f32], hop: usize, tolerance: f32) -> bool {
+    cola_deviation(window, hop) <= tolerance
+}
+
+// Nonzero overlap-add: shifted squared windows never vanish, so WOLA can divide by them
+pub fn is_nola(window: &[f32], hop: usize) -> bool {
+    assert!(hop >= 1, "hop must be >= 1");
+    hop <= window.len() && overlap_sum(window, hop, true).iter().all(|&s| s > 1e-10)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IstftError {
+    // fft_size not a power of 2, or a zero hop
+    InvalidSize { fft_size: usize, hop_size: usize },
+    // Some samples get no window weight, so they cannot be recovered
+    NotNola { fft_size: usize, hop_size: usize },
+    // Spectrum is not num_windows x (fft_size / 2 + 1) bins
+    SpectrumLength { len: usize, expected: usize },
+}
+
+impl fmt::Display for IstftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IstftError::InvalidSize { fft_size, hop_size } => {
+                write!(f, "FFT size must be power of 2 and hop_size >= 1, got {} and {}", fft_size, hop_size)
+            }
+            IstftError::NotNola { fft_size, hop_size } => {
+                write!(f, "Hann window of {} with hop {} violates NOLA", fft_size, hop_size)
+            }
+            IstftError::SpectrumLength { len, expected } => {
+                write!(f, "spectrum must hold num_windows x (fft_size / 2 + 1) = {} bins, got {}", expected, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IstftError {}
+
+pub struct Istft {
+    fft_size: usize,
+    hop_size: usize,
+    window: Vec<f32>,
+    twiddle_cache: TwiddleCache,
+    buffer: Vec<Complex>,
+}
+
+impl Istft {
+    // Create an inverse transform for the processor's Hann window and hop.
+    // Panics if the window/hop pair violates NOLA, since the signal cannot be recovered then.
+    pub fn new(fft_size: usize, hop_size: usize) -> Istft {
+        Istft::try_new(fft_size, hop_size).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // `new` for sizes that come from outside (e.g. JS): an error instead of a panic
+    pub fn try_new(fft_size: usize, hop_size: usize) -> Result<Istft, IstftError> {
+        if !fft_size.is_power_of_two() || hop_size == 0 {
+            return Err(IstftError::InvalidSize { fft_size, hop_size });
+        }
+        let window = generate_hann_window(fft_size);
+        if !is_nola(&window, hop_size) {
+            return Err(IstftError::NotNola { fft_size, hop_size });
+        }
+
+        Ok(Istft {
+            fft_size,
+            hop_size,
+            window,
+            twiddle_cache: TwiddleCache::new(fft_size),
+            buffer: vec![Complex::new(0.0, 0.0); fft_size],
+        })
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    pub fn cola_deviation(&self) -> f32 {
+        cola_deviation(&self.window, self.hop_size)
+    }
+
+    pub fn is_cola(&self, tolerance: f32) -> bool {
+        is_cola(&self.window, self.hop_size, tolerance)
+    }
+
+    // Invert one half spectrum (fft_size / 2 + 1 bins) into the windowed time frame
+    fn inverse_frame(&mut self, bins: &[Complex]) {
+        let n = self.fft_size;
+        let half = n / 2;
+        self.buffer[..=half].copy_from_slice(&bins[..=half]);
+        // Real signal: mirror the conjugate spectrum into the negative frequencies
+        for (k, bin) in bins.iter().enumerate().take(half).skip(1) {
+            self.buffer[n - k] = bin.conj();
+        }
+        ifft_with_cache(&mut self.buffer, &self.twiddle_cache);
+    }
+
+    // Rebuild audio from a window-major complex STFT (`num_windows` x fft_size / 2 + 1).
+    // Samples only touched by the zero-valued window edges come back as 0.0.
+    pub fn process(&mut self, spectrum: &[Complex], num_windows: usize) -> Vec<f32> {
+        self.try_process(spectrum, num_windows).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // `process` with a length mismatch reported instead of panicking
+    pub fn try_process(&mut self, spectrum: &[Complex], num_windows: usize) -> Result<Vec<f32>, IstftError> {
+        let freq_bins = self.fft_size / 2 + 1;
+        if spectrum.len() != num_windows * freq_bins {
+            return Err(IstftError::SpectrumLength {
+                len: spectrum.len(),
+                expected: num_windows * freq_bins,
+            });
+        }
+        if num_windows == 0 {
+            return Ok(Vec::new());
+        }
+
+        let output_len = (num_windows - 1) * self.hop_size + self.fft_size;
+        let mut output = vec![0.0f32; output_len];
+        let mut norm = vec![0.0f32; output_len];
+
+        for (window_idx, bins) in spectrum.chunks_exact(freq_bins).enumerate() {
+            self.inverse_frame(bins);
+            let start = window_idx * self.hop_size;
+            for (i, (&w, sample)) in self.window.iter().zip(self.buffer.iter()).enumerate() {
+                output[start + i] += w * sample.real;
+                norm[start + i] += w * w;
+            }
+        }
+
+        for (sample, &weight) in output.iter_mut().zip(norm.iter()) {
+            *sample = if weight > 1e-10 { *sample / weight } else { 0.0 };
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::SpectrogramProcessor;
+
+    #[test]
+    fn test_hann_cola_and_nola() {
+        let window = generate_hann_window(256);
+
+        // 75% overlap sums to a near constant, no overlap does not
+        assert!(is_cola(&window, 64, 0.02));
+        assert!(!is_cola(&window, 256, 0.02));
+
+        // Symmetric Hann is zero at both ends, so hop == fft_size leaves holes
+        assert!(is_nola(&window, 128));
+        assert!(!is_nola(&window, 256));
+        assert!(!is_nola(&window, 300));
+    }
+
+    #[test]
+    fn test_istft_roundtrip() {
+        let fft_size = 256;
+        let overlap = 0.75;
+        let signal: Vec<f32> = (0..4096)
+            .map(|i| {
+                let t = i as f32 / 4096.0;
+                (2.0 * std::f32::consts::PI * 37.0 * t).sin() + 0.3 * (2.0 * std::f32::consts::PI * 411.0 * t).cos()
+            })
+            .collect();
+
+        let mut processor = SpectrogramProcessor::new(fft_size);
+        let hop = processor.hop_size(overlap);
+        let (spectrum, num_windows, freq_bins) = processor.process_windows_complex(&signal, overlap);
+        assert_eq!(freq_bins, fft_size / 2 + 1);
+
+        let mut istft = Istft::new(fft_size, hop);
+        let rebuilt = istft.process(&spectrum, num_windows);
+        assert_eq!(rebuilt.len(), (num_windows - 1) * hop + fft_size);
+
+        // Everything past the first window edge is covered by at least two windows
+        for i in 1..rebuilt.len() - 1 {
+            assert!(
+                (rebuilt[i] - signal[i]).abs() < 1e-3,
+                "sample {} rebuilt as {} expected {}",
+                i,
+                rebuilt[i],
+                signal[i]
+            );
+        }
+    }
+
+    #[test]
+    fn test_polar_matches_complex() {
+        let signal: Vec<f32> = (0..64).map(|i| (i as f32 * 0.3).sin()).collect();
+        let mut processor = SpectrogramProcessor::new(16);
+
+        let (spectrum, n, bins) = processor.process_windows_complex(&signal, 0.5);
+        let (mags, phases, n2, bins2) = processor.process_windows_polar(&signal, 0.5);
+        assert_eq!((n, bins), (n2, bins2));
+
+        for ((c, m), p) in spectrum.iter().zip(mags.iter()).zip(phases.iter()) {
+            let back = Complex::from_polar(*m, *p);
+            assert!((back.real - c.real).abs() < 1e-4);
+            assert!((back.imag - c.imag).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_invalid_hop_panics() {
+        let result = std::panic::catch_unwind(|| {
+            Istft::new(64, 64);
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_try_new_and_try_process_report_errors() {
+        assert_eq!(Istft::try_new(64, 64).err(), Some(IstftError::NotNola { fft_size: 64, hop_size: 64 }));
+        assert_eq!(Istft::try_new(100, 25).err(), Some(IstftError::InvalidSize { fft_size: 100, hop_size: 25 }));
+        assert_eq!(Istft::try_new(64, 0).err(), Some(IstftError::InvalidSize { fft_size: 64, hop_size: 0 }));
+
+        let mut istft = Istft::try_new(64, 16).unwrap();
+        let spectrum = vec![Complex::new(0.0, 0.0); 2 * 33];
+        assert_eq!(istft.try_process(&spectrum, 3), Err(IstftError::SpectrumLength { len: 66, expected: 99 }));
+        assert_eq!(istft.try_process(&spectrum, 2).map(|out| out.len()), Ok(16 + 64));
+    }
+}
@@ -1,10 +1,78 @@
-pub mod utils;
+// Spektra audio engine
+// Native users get the processor and FFT building blocks below, the web worker
+// gets the wasm-bindgen exports when the `wasm` feature targets wasm32.
+pub mod audio_processor;
+pub mod biquad;
+pub mod bit_depth;
+pub mod clipping;
+pub mod codec_fingerprint;
+pub mod cqt;
+pub(crate) mod db;
+pub mod distortion;
+pub mod dynamics;
+pub mod features;
 pub mod fft;
+pub mod filterbank;
+pub mod framing;
 pub mod hann_window;
-pub mod audio_processor;
+pub mod hires;
+pub mod istft;
+pub mod log_frequency;
+pub mod loudness;
+pub mod multichannel;
+pub mod multires;
+pub mod noise_floor;
+pub mod octave;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod peaks;
+pub mod progress;
+pub mod reassignment;
+pub mod spectrum;
+pub mod transcode;
+pub mod true_peak;
+pub mod utils;
+pub mod weighting;
+pub mod window;
+pub mod zoom;
+
+#[cfg(test)]
+pub(crate) mod test_signals;
+
+pub use audio_processor::SpectrogramProcessor;
+pub use biquad::{Biquad, Sos};
+pub use bit_depth::{BitDepthAnalysis, BitDepthAnalyzer, SourcePattern};
+pub use clipping::{ClipEvent, ClipKind, ClippingAnalysis, ClippingDetector};
+pub use codec_fingerprint::{BitrateClass, CodecClassifier, CodecFingerprint, SourceCodec};
+pub use cqt::ConstantQ;
+pub use distortion::{DistortionAnalysis, DistortionAnalyzer, HarmonicLevel};
+pub use dynamics::{DynamicsAnalysis, DynamicsMeter, RmsHistogram};
+pub use features::{FeatureExtractor, SpectralFeatures};
+pub use fft::{fft, fft_with_cache, ifft, ifft_with_cache, TwiddleCache};
+pub use filterbank::{FilterNorm, Filterbank, FrequencyScale, Mfcc};
+pub use framing::{Framer, SpectrogramStream};
+pub use hires::{HiResAnalysis, HiResDetector, HiResVerdict};
+pub use istft::{Istft, IstftError};
+pub use log_frequency::{LogFrequencyMapper, RowAggregation};
+pub use loudness::{album_replay_gain, LoudnessAnalysis, LoudnessMeter, LoudnessStream, ReplayGain};
+pub use multichannel::{ChannelLayout, MultichannelSpectrogram};
+pub use multires::MultiResolutionStft;
+pub use noise_floor::{BandSnr, FloorMethod, NoiseFloorAnalysis, NoiseFloorEstimator};
+pub use octave::{BandFraction, OctaveBand, OctaveBandAnalysis, OctaveBandAnalyzer};
+pub use peaks::{PeakDetector, PeakInterpolation, SpectralPeak};
+pub use progress::{CancellationToken, ProcessError};
+pub use reassignment::{ReassignmentAnalysis, ReassignmentProcessor};
+pub use spectrum::{LongTermSpectrum, PercentileSpectrum, SpectrumAccumulator};
+pub use transcode::{TranscodeAnalysis, TranscodeDetector, TranscodeVerdict};
+pub use true_peak::{true_peak, true_peak_dbtp, TruePeakMeter};
+pub use utils::Complex;
+pub use weighting::Weighting;
+pub use window::{generate_window, WindowKind};
+pub use zoom::ZoomFft;
 
 // Re-export main WASM interface
-pub use audio_processor::*;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use audio_processor::{SpectrogramBatch, WasmConstantQ, WasmIstft, WasmLogFrequencyMapper, WasmMultiResolutionStft, WasmReassignmentProcessor, WasmSpectrogramProcessor, WasmZoomFft};
 
 #[cfg(test)]
 mod integration_tests {
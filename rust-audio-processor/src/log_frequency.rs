@@ -0,0 +1,192 @@
+// Linear FFT bins -> log-spaced output rows for a log-frequency view
+// Row r covers [min * q^r, min * q^(r+1)) with q = (max / min)^(1 / rows). Rows that span at
+// least one bin aggregate the bins inside them; narrower rows (the bottom octaves)
+// interpolate between the two bins around their centre frequency. The plan is built once
+// per (fft size, range, rows) and reused for every frame.
+use crate::audio_processor::SpectrogramProcessor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RowAggregation {
+    // Power mean of the bins in the row, so levels match the linear view
+    Mean,
+    // Loudest bin, so narrow tones stay as bright as on the linear view
+    Max,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RowSource {
+    Interpolate { bin: usize, fraction: f32 },
+    Aggregate { start: usize, end: usize },
+}
+
+pub struct LogFrequencyMapper {
+    freq_bins: usize,
+    row_hz: Vec<f32>,
+    sources: Vec<RowSource>,
+    aggregation: RowAggregation,
+}
+
+impl LogFrequencyMapper {
+    // Rows for `process_window` output (fft_size / 2 bins) between min_hz and max_hz
+    pub fn new(sample_rate: f32, fft_size: usize, min_hz: f32, max_hz: f32, num_rows: usize) -> LogFrequencyMapper {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        assert!(min_hz > 0.0 && max_hz > min_hz, "need 0 < min_hz < max_hz");
+        assert!(num_rows >= 1, "num_rows must be >= 1");
+
+        let freq_bins = fft_size / 2;
+        let bin_hz = sample_rate / fft_size as f32;
+        let ratio = (max_hz as f64 / min_hz as f64).powf(1.0 / num_rows as f64);
+        let edge = |r: usize| (min_hz as f64 * ratio.powi(r as i32)) as f32;
+        let last_bin = freq_bins.saturating_sub(1);
+
+        let mut row_hz = Vec::with_capacity(num_rows);
+        let mut sources = Vec::with_capacity(num_rows);
+        for r in 0..num_rows {
+            let (lo, hi) = (edge(r) / bin_hz, edge(r + 1) / bin_hz);
+            let centre = (lo * hi).sqrt();
+            row_hz.push(centre * bin_hz);
+            // Bins whose centre falls inside [lo, hi)
+            let start = (lo.ceil() as usize).min(last_bin);
+            let end = (hi.ceil() as usize).min(freq_bins);
+            sources.push(if hi - lo >= 1.0 && end > start {
+                RowSource::Aggregate { start, end }
+            } else {
+                let bin = (centre.floor() as usize).min(last_bin);
+                RowSource::Interpolate {
+                    bin,
+                    fraction: (centre - bin as f32).clamp(0.0, 1.0),
+                }
+            });
+        }
+
+        LogFrequencyMapper {
+            freq_bins,
+            row_hz,
+            sources,
+            aggregation: RowAggregation::Mean,
+        }
+    }
+
+    pub fn with_aggregation(mut self, aggregation: RowAggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.sources.len()
+    }
+
+    // Geometric centre of each row in Hz, bottom row first
+    pub fn row_frequencies(&self) -> &[f32] {
+        &self.row_hz
+    }
+
+    // One frame of fft_size / 2 magnitudes -> num_rows magnitudes
+    pub fn map_frame(&self, magnitudes: &[f32]) -> Vec<f32> {
+        if magnitudes.len() != self.freq_bins {
+            return Vec::new();
+        }
+        self.sources
+            .iter()
+            .map(|source| match *source {
+                RowSource::Interpolate { bin, fraction } => {
+                    let next = magnitudes[(bin + 1).min(self.freq_bins - 1)];
+                    magnitudes[bin] * (1.0 - fraction) + next * fraction
+                }
+                RowSource::Aggregate { start, end } => {
+                    let bins = &magnitudes[start..end];
+                    match self.aggregation {
+                        RowAggregation::Mean => (bins.iter().map(|m| m * m).sum::<f32>() / bins.len() as f32).sqrt(),
+                        RowAggregation::Max => bins.iter().cloned().fold(0.0, f32::max),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Map a whole `process_windows` result (no frequency stride)
+    pub fn map_spectrogram(&self, data: &[f32]) -> (Vec<f32>, usize, usize) {
+        let mut result = Vec::with_capacity(data.len() / self.freq_bins.max(1) * self.num_rows());
+        let mut num_windows = 0;
+        for frame in data.chunks_exact(self.freq_bins.max(1)) {
+            result.extend(self.map_frame(frame));
+            num_windows += 1;
+        }
+        (result, num_windows, self.num_rows())
+    }
+}
+
+impl SpectrogramProcessor {
+    // `process_windows` with log-spaced rows instead of linear bins. The mapping replaces
+    // the frequency stride; the time stride still applies.
+    pub fn process_windows_log(&mut self, audio_data: &[f32], overlap: f32, mapper: &LogFrequencyMapper) -> (Vec<f32>, usize, usize) {
+        assert_eq!(mapper.freq_bins, self.fft_size() / 2, "mapper must be built for the processor's fft_size");
+        self.map_windows(audio_data, overlap, mapper.num_rows(), |magnitudes| mapper.map_frame(magnitudes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{sine, white_noise};
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_rows_are_log_spaced() {
+        let mapper = LogFrequencyMapper::new(SAMPLE_RATE, 4096, 20.0, 20_000.0, 300);
+        let rows = mapper.row_frequencies();
+        assert_eq!(rows.len(), 300);
+        // three decades over 300 rows: 100 rows per decade
+        let ratio = 10f32.powf(0.01);
+        assert!((rows[0] - 20.0 * ratio.sqrt()).abs() < 0.01);
+        for pair in rows.windows(2) {
+            assert!((pair[1] / pair[0] - ratio).abs() < 1e-4);
+        }
+        // 11.7 Hz bins: the bottom rows interpolate, the top ones aggregate
+        assert!(matches!(mapper.sources[0], RowSource::Interpolate { .. }));
+        assert!(matches!(mapper.sources[299], RowSource::Aggregate { .. }));
+    }
+
+    #[test]
+    fn test_tones_land_on_their_rows() {
+        let mapper = LogFrequencyMapper::new(SAMPLE_RATE, 4096, 20.0, 20_000.0, 300).with_aggregation(RowAggregation::Max);
+        for freq in [60.0, 1000.0, 12_000.0] {
+            let signal = sine(freq, 0.5, 4096, SAMPLE_RATE);
+            let rows = mapper.map_frame(&SpectrogramProcessor::new(4096).process_window(&signal));
+            let loudest = rows.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+            // within a bin (or a row, whichever is wider) of the tone
+            let tolerance = (SAMPLE_RATE / 4096.0).max(freq * 0.025);
+            assert!((mapper.row_frequencies()[loudest] - freq).abs() < tolerance, "{} Hz -> {}", freq, mapper.row_frequencies()[loudest]);
+        }
+    }
+
+    #[test]
+    fn test_mean_keeps_noise_level_flat() {
+        let noise = white_noise(1 << 16, 0.5, 41);
+        let mut processor = SpectrogramProcessor::new(2048);
+        let (linear, _, _) = processor.process_windows(&noise, 0.5);
+        let mapper = LogFrequencyMapper::new(SAMPLE_RATE, 2048, 50.0, 20_000.0, 120);
+        let (rows, num_windows, num_rows) = mapper.map_spectrogram(&linear);
+        assert_eq!(num_rows, 120);
+
+        let level_db = |values: &[f32]| 10.0 * (values.iter().map(|m| m * m).sum::<f32>() / values.len() as f32).log10();
+        let overall = level_db(&linear);
+        // every row sits at the same average level, interpolated or aggregated
+        for r in 0..num_rows {
+            let row: Vec<f32> = (0..num_windows).map(|t| rows[t * num_rows + r]).collect();
+            assert!((level_db(&row) - overall).abs() < 1.5, "row {}: {} vs {}", r, level_db(&row), overall);
+        }
+    }
+
+    #[test]
+    fn test_process_windows_log_matches_mapping() {
+        let signal = white_noise(20_000, 0.5, 42);
+        let mapper = LogFrequencyMapper::new(SAMPLE_RATE, 1024, 30.0, 16_000.0, 64);
+        let mut processor = SpectrogramProcessor::new(1024).with_strides(2, 1);
+        let (linear, _, _) = processor.process_windows(&signal, 0.5);
+        assert_eq!(processor.process_windows_log(&signal, 0.5, &mapper), mapper.map_spectrogram(&linear));
+    }
+}
@@ -0,0 +1,393 @@
+// ITU-R BS.1770 / EBU R128 loudness: K-weighting, gated integrated loudness,
+// momentary (400 ms) and short-term (3 s) series, loudness range, and ReplayGain 2.0
+// track / album gain derived from the same measurements
+use crate::biquad::{k_weighting, Biquad};
+use crate::db::FLOOR_DB;
+use crate::framing::Framer;
+use crate::multichannel::{BlockDeinterleaver, ChannelLayout};
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+// ReplayGain 2.0 reference level
+pub const REPLAY_GAIN_REFERENCE_LUFS: f32 = -18.0;
+
+// Every series advances in 100 ms steps; the first value covers the first full window
+const SUBBLOCKS_PER_SECOND: usize = 10;
+const MOMENTARY_SUBBLOCKS: usize = 4;
+const SHORT_TERM_SUBBLOCKS: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayGain {
+    // Gain to bring the programme to -18 LUFS
+    pub gain_db: f32,
+    // Linear sample peak
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoudnessAnalysis {
+    // None when every block falls under the absolute gate (silence)
+    pub integrated_lufs: Option<f32>,
+    // One value per 100 ms, clamped to FLOOR_DB for digital silence
+    pub momentary_lufs: Vec<f32>,
+    pub short_term_lufs: Vec<f32>,
+    pub max_momentary_lufs: f32,
+    pub max_short_term_lufs: f32,
+    pub loudness_range_lu: f32,
+    pub sample_peak: f32,
+    pub replay_gain: Option<ReplayGain>,
+    // Weighted mean square of every 400 ms gating block, kept so album loudness can
+    // gate the blocks of all tracks together
+    pub gating_blocks: Vec<f64>,
+}
+
+pub struct LoudnessMeter {
+    sample_rate: f32,
+    channel_weights: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    // Default BS.1770 weights: 1.0 for front channels, 1.41 for surrounds, LFE
+    // (channel 4 of 5.1 in WAV order) excluded
+    pub fn new(sample_rate: f32, num_channels: usize) -> LoudnessMeter {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(num_channels >= 1, "num_channels must be >= 1");
+        let channel_weights = match num_channels {
+            5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+            6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+            n => vec![1.0; n],
+        };
+        LoudnessMeter {
+            sample_rate,
+            channel_weights,
+        }
+    }
+
+    pub fn with_channel_weights(mut self, weights: &[f64]) -> Self {
+        assert_eq!(weights.len(), self.channel_weights.len(), "one weight per channel");
+        self.channel_weights = weights.to_vec();
+        self
+    }
+
+    pub fn measure(&self, data: &[f32], layout: ChannelLayout) -> LoudnessAnalysis {
+        let mut stream = self.stream();
+        match layout {
+            ChannelLayout::Interleaved => stream.push(data),
+            // Empty or ragged planar data has no consistent channel offsets, so it
+            // measures as silence, like `process_windows_multichannel`
+            ChannelLayout::Planar => {
+                let num_channels = self.channel_weights.len();
+                if !data.is_empty() && data.len().is_multiple_of(num_channels) {
+                    let planes: Vec<&[f32]> = data.chunks_exact(data.len() / num_channels).collect();
+                    stream.push_channels(&planes);
+                }
+            }
+        }
+        stream.finish()
+    }
+
+    pub fn measure_channels(&self, channels: &[&[f32]]) -> LoudnessAnalysis {
+        let mut stream = self.stream();
+        stream.push_channels(channels);
+        stream.finish()
+    }
+
+    // Incremental measurement for decoded blocks as they arrive
+    pub fn stream(&self) -> LoudnessStream {
+        let subblock_len = (self.sample_rate as f64 / SUBBLOCKS_PER_SECOND as f64).round() as usize;
+        LoudnessStream {
+            channels: self
+                .channel_weights
+                .iter()
+                .map(|_| ChannelState {
+                    framer: Framer::new(subblock_len, subblock_len),
+                    filters: k_weighting(self.sample_rate as f64),
+                    energies: Vec::new(),
+                })
+                .collect(),
+            channel_weights: self.channel_weights.clone(),
+            deinterleaver: BlockDeinterleaver::new(self.channel_weights.len()),
+            sample_peak: 0.0,
+        }
+    }
+}
+
+struct ChannelState {
+    framer: Framer,
+    filters: [Biquad; 2],
+    // mean square of the K-weighted signal per 100 ms sub-block
+    energies: Vec<f64>,
+}
+
+pub struct LoudnessStream {
+    channel_weights: Vec<f64>,
+    channels: Vec<ChannelState>,
+    deinterleaver: BlockDeinterleaver,
+    sample_peak: f32,
+}
+
+impl LoudnessStream {
+    // Blocks may end mid-frame; the partial frame waits for the next push
+    pub fn push(&mut self, interleaved: &[f32]) {
+        let channels = self.deinterleaver.push(interleaved);
+        let planes: Vec<&[f32]> = channels.iter().map(|c| c.as_slice()).collect();
+        self.push_channels(&planes);
+    }
+
+    pub fn push_channels(&mut self, channels: &[&[f32]]) {
+        assert_eq!(channels.len(), self.channels.len(), "channel count does not match the meter");
+        for ((state, samples), &weight) in self.channels.iter_mut().zip(channels.iter()).zip(self.channel_weights.iter()) {
+            self.sample_peak = samples.iter().fold(self.sample_peak, |peak, s| peak.max(s.abs()));
+            if weight == 0.0 {
+                continue;
+            }
+            let ChannelState { framer, filters, energies } = state;
+            let [shelf, highpass] = filters;
+            framer.push(samples, |_, subblock| {
+                let sum: f64 = subblock
+                    .iter()
+                    .map(|&s| {
+                        let y = highpass.process(shelf.process(s as f64));
+                        y * y
+                    })
+                    .sum();
+                energies.push(sum / subblock.len() as f64);
+            });
+        }
+    }
+
+    // A trailing partial sub-block is dropped
+    pub fn finish(self) -> LoudnessAnalysis {
+        let num_subblocks = self
+            .channels
+            .iter()
+            .zip(self.channel_weights.iter())
+            .filter(|(_, &w)| w != 0.0)
+            .map(|(c, _)| c.energies.len())
+            .min()
+            .unwrap_or(0);
+        let mut subblocks = vec![0.0f64; num_subblocks];
+        for (state, &weight) in self.channels.iter().zip(self.channel_weights.iter()) {
+            if weight == 0.0 {
+                continue;
+            }
+            for (energy, &e) in subblocks.iter_mut().zip(state.energies.iter()) {
+                *energy += weight * e;
+            }
+        }
+
+        let gating_blocks = sliding_means(&subblocks, MOMENTARY_SUBBLOCKS);
+        let short_term = sliding_means(&subblocks, SHORT_TERM_SUBBLOCKS);
+        let momentary_lufs: Vec<f32> = gating_blocks.iter().map(|&e| energy_to_lufs(e)).collect();
+        let short_term_lufs: Vec<f32> = short_term.iter().map(|&e| energy_to_lufs(e)).collect();
+        let integrated_lufs = gated_loudness(&gating_blocks);
+        let sample_peak = self.sample_peak;
+
+        LoudnessAnalysis {
+            integrated_lufs,
+            max_momentary_lufs: momentary_lufs.iter().cloned().fold(FLOOR_DB, f32::max),
+            max_short_term_lufs: short_term_lufs.iter().cloned().fold(FLOOR_DB, f32::max),
+            momentary_lufs,
+            short_term_lufs,
+            loudness_range_lu: loudness_range(&short_term),
+            sample_peak,
+            replay_gain: integrated_lufs.map(|lufs| ReplayGain {
+                gain_db: REPLAY_GAIN_REFERENCE_LUFS - lufs,
+                peak: sample_peak,
+            }),
+            gating_blocks,
+        }
+    }
+}
+
+// ReplayGain 2.0 album gain: one integrated loudness over the pooled gating blocks of
+// every track (so long tracks weigh more), peak is the loudest track peak
+pub fn album_replay_gain(tracks: &[LoudnessAnalysis]) -> Option<ReplayGain> {
+    let pooled: Vec<f64> = tracks.iter().flat_map(|t| t.gating_blocks.iter().cloned()).collect();
+    let peak = tracks.iter().fold(0.0f32, |peak, t| peak.max(t.sample_peak));
+    gated_loudness(&pooled).map(|lufs| ReplayGain {
+        gain_db: REPLAY_GAIN_REFERENCE_LUFS - lufs,
+        peak,
+    })
+}
+
+pub fn album_integrated_lufs(tracks: &[LoudnessAnalysis]) -> Option<f32> {
+    let pooled: Vec<f64> = tracks.iter().flat_map(|t| t.gating_blocks.iter().cloned()).collect();
+    gated_loudness(&pooled)
+}
+
+fn energy_to_lufs(energy: f64) -> f32 {
+    if energy > 0.0 {
+        ((-0.691 + 10.0 * energy.log10()) as f32).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
+
+fn lufs_to_energy(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+// Mean of every run of `len` consecutive sub-blocks (hop of one sub-block)
+fn sliding_means(subblocks: &[f64], len: usize) -> Vec<f64> {
+    subblocks.windows(len).map(|w| w.iter().sum::<f64>() / len as f64).collect()
+}
+
+// BS.1770 two-stage gating: absolute gate at -70 LUFS, then a relative gate 10 LU
+// under the loudness of the blocks that survived the first one
+fn gated_loudness(blocks: &[f64]) -> Option<f32> {
+    let absolute = lufs_to_energy(ABSOLUTE_GATE_LUFS);
+    let above: Vec<f64> = blocks.iter().cloned().filter(|&e| e > absolute).collect();
+    if above.is_empty() {
+        return None;
+    }
+    let ungated_mean = above.iter().sum::<f64>() / above.len() as f64;
+    let relative = ungated_mean * 10f64.powf(RELATIVE_GATE_LU / 10.0);
+    let gated: Vec<f64> = above.into_iter().filter(|&e| e > relative).collect();
+    let mean = gated.iter().sum::<f64>() / gated.len() as f64;
+    Some(energy_to_lufs(mean))
+}
+
+// EBU Tech 3342: short-term values gated at -70 LUFS and 20 LU under their mean,
+// then the spread between the 10th and 95th percentiles
+fn loudness_range(short_term: &[f64]) -> f32 {
+    let absolute = lufs_to_energy(ABSOLUTE_GATE_LUFS);
+    let above: Vec<f64> = short_term.iter().cloned().filter(|&e| e > absolute).collect();
+    if above.is_empty() {
+        return 0.0;
+    }
+    let mean = above.iter().sum::<f64>() / above.len() as f64;
+    let relative = mean * 10f64.powf(LRA_RELATIVE_GATE_LU / 10.0);
+    let mut gated: Vec<f32> = above.into_iter().filter(|&e| e > relative).map(energy_to_lufs).collect();
+    gated.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f32| gated[((gated.len() - 1) as f32 * p).round() as usize];
+    percentile(0.95) - percentile(0.10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    // Stereo 1 kHz tone sections as (dBFS per channel, seconds), planar, like Tech 3341/3342
+    fn tone_sections(sections: &[(f32, f32)]) -> Vec<f32> {
+        let mono: Vec<f32> = sections
+            .iter()
+            .flat_map(|&(level_db, seconds)| {
+                let amplitude = 10f32.powf(level_db / 20.0);
+                (0..(seconds * SAMPLE_RATE) as usize).map(move |i| amplitude * (2.0 * PI * 1000.0 * i as f32 / SAMPLE_RATE).sin())
+            })
+            .collect();
+        [mono.clone(), mono].concat()
+    }
+
+    fn integrated(sections: &[(f32, f32)]) -> f32 {
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        meter.measure(&tone_sections(sections), ChannelLayout::Planar).integrated_lufs.unwrap()
+    }
+
+    #[test]
+    fn test_tech_3341_steady_tones() {
+        // Cases 1 and 2: -23 and -33 dBFS tones read -23 and -33 LUFS (M, S and I)
+        let analysis = LoudnessMeter::new(SAMPLE_RATE, 2).measure(&tone_sections(&[(-23.0, 20.0)]), ChannelLayout::Planar);
+        assert!((analysis.integrated_lufs.unwrap() + 23.0).abs() < 0.1);
+        assert!(analysis.momentary_lufs.iter().all(|m| (m + 23.0).abs() < 0.1));
+        assert!(analysis.short_term_lufs.iter().all(|s| (s + 23.0).abs() < 0.1));
+        assert!((integrated(&[(-33.0, 20.0)]) + 33.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_tech_3341_gating() {
+        // Case 3: relative gate drops the -36 dBFS sections
+        assert!((integrated(&[(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)]) + 23.0).abs() < 0.1);
+        // Case 4: absolute gate drops the -72 dBFS sections as well
+        let case_4 = [(-72.0, 10.0), (-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0), (-72.0, 10.0)];
+        assert!((integrated(&case_4) + 23.0).abs() < 0.1);
+        // Case 5: the -20 dBFS section averages with the -26 ones
+        assert!((integrated(&[(-26.0, 20.0), (-20.0, 20.1), (-26.0, 20.0)]) + 23.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_tech_3342_loudness_range() {
+        // Cases 1 and 2: 20 s at -20 dBFS then 20 s at -30 / -15 dBFS
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let lra = |second: f32| {
+            meter
+                .measure(&tone_sections(&[(-20.0, 20.0), (second, 20.0)]), ChannelLayout::Planar)
+                .loudness_range_lu
+        };
+        assert!((lra(-30.0) - 10.0).abs() < 1.0, "{}", lra(-30.0));
+        assert!((lra(-15.0) - 5.0).abs() < 1.0, "{}", lra(-15.0));
+    }
+
+    #[test]
+    fn test_replay_gain_track_and_album() {
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let loud = meter.measure(&tone_sections(&[(-10.0, 10.0)]), ChannelLayout::Planar);
+        let quiet = meter.measure(&tone_sections(&[(-20.0, 10.0)]), ChannelLayout::Planar);
+
+        let loud_gain = loud.replay_gain.unwrap();
+        assert!((loud_gain.gain_db + 8.0).abs() < 0.1, "{}", loud_gain.gain_db);
+        assert!((loud_gain.peak - 10f32.powf(-0.5)).abs() < 1e-3);
+        assert!((quiet.replay_gain.unwrap().gain_db - 2.0).abs() < 0.1);
+
+        // The quiet track is 10 LU down so it survives the relative gate and pulls the
+        // album a little under the loud track
+        let album = album_replay_gain(&[loud.clone(), quiet]).unwrap();
+        assert!(album.gain_db > loud_gain.gain_db && album.gain_db < loud_gain.gain_db + 3.0, "{}", album.gain_db);
+        assert_eq!(album.peak, loud_gain.peak);
+    }
+
+    #[test]
+    fn test_stream_matches_buffer() {
+        let planar = tone_sections(&[(-30.0, 3.0), (-20.0, 4.0)]);
+        let (left, right) = planar.split_at(planar.len() / 2);
+        let interleaved: Vec<f32> = left.iter().zip(right.iter()).flat_map(|(&l, &r)| [l, r]).collect();
+
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let mut stream = meter.stream();
+        for block in interleaved.chunks(2 * 1777) {
+            stream.push(block);
+        }
+        assert_eq!(stream.finish(), meter.measure(&planar, ChannelLayout::Planar));
+    }
+
+    #[test]
+    fn test_stream_carries_partial_frames() {
+        let planar = tone_sections(&[(-10.0, 3.0)]);
+        let (left, right) = planar.split_at(planar.len() / 2);
+        let interleaved: Vec<f32> = left.iter().zip(right.iter()).flat_map(|(&l, &r)| [l, r]).collect();
+
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let mut whole = meter.stream();
+        whole.push(&interleaved);
+        let mut blocks = meter.stream();
+        for block in interleaved.chunks(1023) {
+            blocks.push(block);
+        }
+        assert_eq!(blocks.finish(), whole.finish());
+    }
+
+    #[test]
+    fn test_empty_and_ragged_planar_are_silence() {
+        let meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let silence = meter.measure(&[], ChannelLayout::Interleaved);
+        assert_eq!(silence.integrated_lufs, None);
+        assert_eq!(meter.measure(&[], ChannelLayout::Planar), silence);
+        assert_eq!(meter.measure(&[0.1], ChannelLayout::Planar), silence);
+        assert_eq!(meter.measure(&[0.1; 9_601], ChannelLayout::Planar), silence);
+    }
+
+    #[test]
+    fn test_silence_has_no_integrated_loudness() {
+        let analysis = LoudnessMeter::new(SAMPLE_RATE, 1).measure(&vec![0.0; 48_000], ChannelLayout::Interleaved);
+        assert_eq!(analysis.integrated_lufs, None);
+        assert_eq!(analysis.replay_gain, None);
+        assert!(analysis.momentary_lufs.iter().all(|&m| m == FLOOR_DB));
+    }
+}
@@ -0,0 +1,268 @@
+// Multichannel spectrograms: per-channel, downmix and mid/side in one pass over the windows
+use crate::audio_processor::SpectrogramProcessor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    // L R L R ... (Web Audio / WAV frame order)
+    Interleaved,
+    // all of channel 0, then all of channel 1, ...
+    Planar,
+}
+
+// Every plane is flattened window-major like `process_windows` (num_windows x freq_bins)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultichannelSpectrogram {
+    pub channels: Vec<Vec<f32>>,
+    pub downmix: Vec<f32>,
+    // Only for stereo input: mid = (L + R) / 2, side = (L - R) / 2
+    pub mid: Option<Vec<f32>>,
+    pub side: Option<Vec<f32>>,
+    pub num_windows: usize,
+    pub freq_bins: usize,
+}
+
+impl MultichannelSpectrogram {
+    // Planes stacked as channels, downmix, then mid and side when present
+    pub fn planes(&self) -> Vec<&[f32]> {
+        let mut planes: Vec<&[f32]> = self.channels.iter().map(|c| c.as_slice()).collect();
+        planes.push(&self.downmix);
+        if let (Some(mid), Some(side)) = (&self.mid, &self.side) {
+            planes.push(mid);
+            planes.push(side);
+        }
+        planes
+    }
+}
+
+// Split interleaved frames into one Vec per channel
+pub fn deinterleave(data: &[f32], num_channels: usize) -> Vec<Vec<f32>> {
+    assert!(num_channels >= 1, "num_channels must be >= 1");
+    let frames = data.len() / num_channels;
+    (0..num_channels)
+        .map(|ch| (0..frames).map(|i| data[i * num_channels + ch]).collect())
+        .collect()
+}
+
+// Deinterleaves a stream of blocks for the streaming meters. Blocks need not end on a
+// frame boundary: the trailing partial frame is held back and completed by the next push.
+pub(crate) struct BlockDeinterleaver {
+    num_channels: usize,
+    pending: Vec<f32>,
+}
+
+impl BlockDeinterleaver {
+    pub(crate) fn new(num_channels: usize) -> BlockDeinterleaver {
+        assert!(num_channels >= 1, "num_channels must be >= 1");
+        BlockDeinterleaver {
+            num_channels,
+            pending: Vec::with_capacity(num_channels),
+        }
+    }
+
+    // One Vec per channel holding every frame completed by `interleaved`
+    pub(crate) fn push(&mut self, interleaved: &[f32]) -> Vec<Vec<f32>> {
+        let n = self.num_channels;
+        let mut planes = vec![Vec::with_capacity((self.pending.len() + interleaved.len()) / n); n];
+        let mut rest = interleaved;
+        if !self.pending.is_empty() {
+            let needed = (n - self.pending.len()).min(rest.len());
+            self.pending.extend_from_slice(&rest[..needed]);
+            rest = &rest[needed..];
+            if self.pending.len() < n {
+                return planes;
+            }
+            for (plane, &s) in planes.iter_mut().zip(self.pending.iter()) {
+                plane.push(s);
+            }
+            self.pending.clear();
+        }
+        let whole = rest.len() - rest.len() % n;
+        for frame in rest[..whole].chunks_exact(n) {
+            for (plane, &s) in planes.iter_mut().zip(frame.iter()) {
+                plane.push(s);
+            }
+        }
+        self.pending.extend_from_slice(&rest[whole..]);
+        planes
+    }
+}
+
+impl SpectrogramProcessor {
+    // Spectrograms for every channel plus downmix (and mid/side for stereo).
+    // Honours the processor strides the same way `process_windows` does.
+    pub fn process_windows_multichannel(
+        &mut self,
+        data: &[f32],
+        num_channels: usize,
+        layout: ChannelLayout,
+        overlap: f32,
+    ) -> MultichannelSpectrogram {
+        assert!(num_channels >= 1, "num_channels must be >= 1");
+        let fft_size = self.fft_size();
+        let freq_bins = fft_size / 2;
+        let reduced_bins = freq_bins.div_ceil(self.freq_stride());
+        // Ragged planar data has no consistent channel offsets, so it reads as empty
+        // rather than bleeding one channel into the next
+        let frames = match layout {
+            ChannelLayout::Planar if !data.len().is_multiple_of(num_channels) => 0,
+            _ => data.len() / num_channels,
+        };
+        let stereo = num_channels == 2;
+
+        let sample = |ch: usize, i: usize| match layout {
+            ChannelLayout::Interleaved => data[i * num_channels + ch],
+            ChannelLayout::Planar => data[ch * frames + i],
+        };
+
+        let starts = self.window_starts(frames, overlap);
+        let num_windows = starts.len();
+
+        let mut channels = vec![Vec::with_capacity(num_windows * reduced_bins); num_channels];
+        let mut downmix = Vec::with_capacity(num_windows * reduced_bins);
+        let mut side = Vec::new();
+
+        let mut frame = vec![0.0f32; fft_size];
+        let mut mix_frame = vec![0.0f32; fft_size];
+        let mut side_frame = vec![0.0f32; fft_size];
+        let freq_stride = self.freq_stride();
+
+        for start in starts {
+            mix_frame.iter_mut().for_each(|s| *s = 0.0);
+
+            for (ch, plane) in channels.iter_mut().enumerate() {
+                for (i, slot) in frame.iter_mut().enumerate() {
+                    *slot = sample(ch, start + i);
+                }
+                for (mix, &s) in mix_frame.iter_mut().zip(frame.iter()) {
+                    *mix += s / num_channels as f32;
+                }
+                plane.extend(self.process_window(&frame).into_iter().step_by(freq_stride));
+            }
+
+            downmix.extend(self.process_window(&mix_frame).into_iter().step_by(freq_stride));
+
+            if stereo {
+                for (i, slot) in side_frame.iter_mut().enumerate() {
+                    *slot = 0.5 * (sample(0, start + i) - sample(1, start + i));
+                }
+                side.extend(self.process_window(&side_frame).into_iter().step_by(freq_stride));
+            }
+        }
+
+        // For stereo the downmix is exactly (L + R) / 2, so it doubles as mid
+        let (mid, side) = if stereo {
+            (Some(downmix.clone()), Some(side))
+        } else {
+            (None, None)
+        };
+
+        MultichannelSpectrogram {
+            channels,
+            downmix,
+            mid,
+            side,
+            num_windows,
+            freq_bins: reduced_bins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn tone(len: usize, cycles: f32) -> Vec<f32> {
+        (0..len).map(|i| (2.0 * PI * cycles * i as f32 / len as f32).sin()).collect()
+    }
+
+    fn interleave(left: &[f32], right: &[f32]) -> Vec<f32> {
+        left.iter().zip(right.iter()).flat_map(|(&l, &r)| [l, r]).collect()
+    }
+
+    #[test]
+    fn test_channels_match_mono_processing() {
+        let left = tone(256, 8.0);
+        let right = tone(256, 20.0);
+        let mut processor = SpectrogramProcessor::new(32);
+
+        let result = processor.process_windows_multichannel(&interleave(&left, &right), 2, ChannelLayout::Interleaved, 0.5);
+        let (left_only, num_windows, freq_bins) = processor.process_windows(&left, 0.5);
+        let (right_only, _, _) = processor.process_windows(&right, 0.5);
+
+        assert_eq!(result.num_windows, num_windows);
+        assert_eq!(result.freq_bins, freq_bins);
+        assert_eq!(result.channels[0], left_only);
+        assert_eq!(result.channels[1], right_only);
+        assert_eq!(result.planes().len(), 5);
+    }
+
+    #[test]
+    fn test_planar_matches_interleaved() {
+        let left = tone(128, 4.0);
+        let right = tone(128, 9.0);
+        let mut planar = left.clone();
+        planar.extend_from_slice(&right);
+        let mut processor = SpectrogramProcessor::new(16).with_strides(2, 2);
+
+        let a = processor.process_windows_multichannel(&interleave(&left, &right), 2, ChannelLayout::Interleaved, 0.5);
+        let b = processor.process_windows_multichannel(&planar, 2, ChannelLayout::Planar, 0.5);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_ragged_planar_is_empty() {
+        let mut planar = tone(128, 4.0);
+        planar.extend(tone(127, 9.0));
+        let result = SpectrogramProcessor::new(16).process_windows_multichannel(&planar, 2, ChannelLayout::Planar, 0.5);
+        assert_eq!(result.num_windows, 0);
+        assert!(result.channels.iter().all(|c| c.is_empty()) && result.downmix.is_empty());
+    }
+
+    #[test]
+    fn test_mid_side() {
+        let left = tone(256, 8.0);
+        let inverted: Vec<f32> = left.iter().map(|s| -s).collect();
+        let mut processor = SpectrogramProcessor::new(32);
+
+        // Identical channels: all energy in mid
+        let mono = processor.process_windows_multichannel(&interleave(&left, &left), 2, ChannelLayout::Interleaved, 0.5);
+        assert!(mono.side.as_ref().unwrap().iter().all(|&m| m < 1e-5));
+        assert!(mono.mid.as_ref().unwrap().iter().any(|&m| m > 1.0));
+
+        // Out of phase channels: all energy in side
+        let wide = processor.process_windows_multichannel(&interleave(&left, &inverted), 2, ChannelLayout::Interleaved, 0.5);
+        assert!(wide.mid.as_ref().unwrap().iter().all(|&m| m < 1e-5));
+        assert!(wide.side.as_ref().unwrap().iter().any(|&m| m > 1.0));
+    }
+
+    #[test]
+    fn test_mono_and_surround_have_no_mid_side() {
+        let mut processor = SpectrogramProcessor::new(16);
+        let six = vec![0.25f32; 6 * 64];
+        let result = processor.process_windows_multichannel(&six, 6, ChannelLayout::Interleaved, 0.5);
+        assert_eq!(result.channels.len(), 6);
+        assert!(result.mid.is_none() && result.side.is_none());
+        assert_eq!(result.downmix.len(), result.num_windows * result.freq_bins);
+    }
+
+    #[test]
+    fn test_block_deinterleaver_carries_partial_frames() {
+        let data: Vec<f32> = (0..30).map(|i| i as f32).collect();
+        let mut deinterleaver = BlockDeinterleaver::new(3);
+        let mut planes = vec![Vec::new(); 3];
+        for block in [&data[..4], &data[4..5], &data[5..5], &data[5..17], &data[17..]] {
+            for (plane, part) in planes.iter_mut().zip(deinterleaver.push(block)) {
+                plane.extend(part);
+            }
+        }
+        assert_eq!(planes, deinterleave(&data, 3));
+    }
+
+    #[test]
+    fn test_deinterleave() {
+        let planes = deinterleave(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3);
+        assert_eq!(planes, vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]);
+    }
+}
@@ -0,0 +1,226 @@
+// Multi-resolution STFT: several FFT sizes stitched by frequency region
+// Long windows cover the lows, short ones the highs. Every size runs at the same hop with
+// its frame centred on the longest frame, so row t of every size describes the same
+// instant. Each size keeps its own processor (window + twiddle cache), built once. Short
+// FFTs are interpolated onto the longest FFT's bin grid and scaled by the window sums so
+// a tone reads the same level in every region.
+use crate::audio_processor::{frame_starts, SpectrogramProcessor};
+
+pub struct MultiResolutionStft {
+    sample_rate: f32,
+    // Longest first, one per frequency region
+    processors: Vec<SpectrogramProcessor>,
+    // Ascending, one fewer than processors
+    crossovers_hz: Vec<f32>,
+    crossfade_octaves: f32,
+    // Amplitude factor per processor that matches its tone level to the longest window
+    gains: Vec<f32>,
+    // freq_bins x processors region weights, bin-major
+    blend: Vec<f32>,
+}
+
+impl MultiResolutionStft {
+    // `fft_sizes[i]` covers crossovers_hz[i - 1]..crossovers_hz[i]. Sizes must shrink
+    // as the crossovers rise.
+    pub fn new(sample_rate: f32, fft_sizes: &[usize], crossovers_hz: &[f32]) -> MultiResolutionStft {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(!fft_sizes.is_empty(), "need at least one FFT size");
+        assert_eq!(crossovers_hz.len() + 1, fft_sizes.len(), "need one crossover between each pair of FFT sizes");
+        assert!(fft_sizes.windows(2).all(|pair| pair[0] > pair[1]), "FFT sizes must be strictly decreasing");
+        assert!(
+            crossovers_hz.windows(2).all(|pair| pair[0] < pair[1]) && crossovers_hz.iter().all(|&f| f > 0.0 && f < sample_rate / 2.0),
+            "crossovers must be ascending and inside (0, Nyquist)"
+        );
+
+        let processors: Vec<SpectrogramProcessor> = fft_sizes.iter().map(|&size| SpectrogramProcessor::new(size)).collect();
+        let window_sum = |p: &SpectrogramProcessor| p.window().iter().sum::<f32>();
+        let reference = window_sum(&processors[0]);
+        let gains = processors.iter().map(|p| reference / window_sum(p)).collect();
+
+        let mut stft = MultiResolutionStft {
+            sample_rate,
+            processors,
+            crossovers_hz: crossovers_hz.to_vec(),
+            crossfade_octaves: 0.5,
+            gains,
+            blend: Vec::new(),
+        };
+        stft.configure();
+        stft
+    }
+
+    // Width of the raised-cosine crossfade centred on each crossover (0 = hard switch)
+    pub fn with_crossfade(mut self, octaves: f32) -> Self {
+        assert!(octaves >= 0.0, "crossfade must be >= 0 octaves");
+        self.crossfade_octaves = octaves;
+        self.configure();
+        self
+    }
+
+    pub fn fft_sizes(&self) -> Vec<usize> {
+        self.processors.iter().map(|p| p.fft_size()).collect()
+    }
+
+    pub fn crossovers(&self) -> &[f32] {
+        &self.crossovers_hz
+    }
+
+    // Output bins: the longest FFT's fft_size / 2
+    pub fn freq_bins(&self) -> usize {
+        self.processors[0].fft_size() / 2
+    }
+
+    // Shared hop, taken from the shortest window so the highs keep their time resolution
+    pub fn hop_size(&self, overlap: f32) -> usize {
+        self.processors[self.processors.len() - 1].hop_size(overlap)
+    }
+
+    // Weight of each region at one output bin; always sums to 1
+    fn configure(&mut self) {
+        let freq_bins = self.freq_bins();
+        let bin_hz = self.sample_rate / self.processors[0].fft_size() as f32;
+        let regions = self.processors.len();
+        self.blend = vec![0.0; freq_bins * regions];
+        for k in 0..freq_bins {
+            // above[j]: how far bin k has crossed crossover j, 0..1
+            let freq = k as f32 * bin_hz;
+            let above: Vec<f32> = self.crossovers_hz.iter().map(|&c| self.crossfade(freq, c)).collect();
+            for r in 0..regions {
+                let entered = if r == 0 { 1.0 } else { above[r - 1] };
+                let left = above.get(r).copied().unwrap_or(0.0);
+                self.blend[k * regions + r] = entered - left;
+            }
+        }
+    }
+
+    fn crossfade(&self, freq: f32, crossover: f32) -> f32 {
+        if freq <= 0.0 {
+            return 0.0;
+        }
+        let octaves = (freq / crossover).log2();
+        if self.crossfade_octaves <= 0.0 {
+            return if octaves >= 0.0 { 1.0 } else { 0.0 };
+        }
+        let x = (octaves / self.crossfade_octaves + 0.5).clamp(0.0, 1.0);
+        0.5 - 0.5 * (std::f32::consts::PI * x).cos()
+    }
+
+    // Blended magnitudes: num_windows x freq_bins, flattened window-major, on the same
+    // frames as `process_windows` of the longest size at the shared hop
+    pub fn process_windows(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, usize, usize) {
+        let hop_size = self.hop_size(overlap);
+        let longest = self.processors[0].fft_size();
+        let freq_bins = self.freq_bins();
+        let regions = self.processors.len();
+
+        let starts = frame_starts(audio_data.len(), longest, hop_size);
+        let num_windows = starts.len();
+        let mut result = Vec::with_capacity(num_windows * freq_bins);
+        let mut frames: Vec<Vec<f32>> = vec![Vec::new(); regions];
+
+        for start in starts {
+            for (frame, processor) in frames.iter_mut().zip(self.processors.iter_mut()) {
+                let size = processor.fft_size();
+                let offset = start + (longest - size) / 2;
+                *frame = processor.process_window(&audio_data[offset..offset + size]);
+            }
+
+            for k in 0..freq_bins {
+                let mut value = 0.0;
+                for (r, frame) in frames.iter().enumerate() {
+                    let weight = self.blend[k * regions + r];
+                    if weight > 0.0 {
+                        // Output bin k sits at k * size / longest in this FFT
+                        let position = k as f32 * frame.len() as f32 / freq_bins as f32;
+                        let bin = (position as usize).min(frame.len() - 1);
+                        let fraction = position - bin as f32;
+                        let next = frame[(bin + 1).min(frame.len() - 1)];
+                        value += weight * self.gains[r] * (frame[bin] * (1.0 - fraction) + next * fraction);
+                    }
+                }
+                result.push(value);
+            }
+        }
+
+        (result, num_windows, freq_bins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::sine;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_blend_weights() {
+        let stft = MultiResolutionStft::new(SAMPLE_RATE, &[8192, 2048, 512], &[300.0, 2000.0]);
+        assert_eq!(stft.freq_bins(), 4096);
+        assert_eq!(stft.hop_size(0.5), 256);
+        let bin = |hz: f32| (hz / (SAMPLE_RATE / 8192.0)) as usize;
+        for k in 0..4096 {
+            let sum: f32 = stft.blend[k * 3..k * 3 + 3].iter().sum();
+            assert!((sum - 1.0).abs() < 1e-5);
+        }
+        assert_eq!(&stft.blend[bin(100.0) * 3..bin(100.0) * 3 + 3], &[1.0, 0.0, 0.0]);
+        assert_eq!(&stft.blend[bin(10_000.0) * 3..bin(10_000.0) * 3 + 3], &[0.0, 0.0, 1.0]);
+        // halfway at the crossover itself
+        assert!((stft.blend[bin(2000.0) * 3 + 1] - 0.5).abs() < 0.05);
+
+        let hard = MultiResolutionStft::new(SAMPLE_RATE, &[8192, 2048, 512], &[300.0, 2000.0]).with_crossfade(0.0);
+        assert!(hard.blend.iter().all(|&w| w == 0.0 || w == 1.0));
+    }
+
+    #[test]
+    fn test_regions_come_from_their_sizes() {
+        let signal = sine(440.0, 0.5, 40_000, SAMPLE_RATE);
+        let mut stft = MultiResolutionStft::new(SAMPLE_RATE, &[4096, 512], &[1000.0]).with_crossfade(0.0);
+        let (blended, num_windows, freq_bins) = stft.process_windows(&signal, 0.5);
+        assert_eq!(num_windows, (40_000 - 4096) / 256 + 1);
+
+        // the lows are exactly the 4096 spectrogram at the shared hop
+        let mut long = SpectrogramProcessor::new(4096);
+        let low_bins = (1000.0 / (SAMPLE_RATE / 4096.0)) as usize;
+        for t in [0, num_windows / 2, num_windows - 1] {
+            let frame = long.process_window(&signal[t * 256..t * 256 + 4096]);
+            assert_eq!(&blended[t * freq_bins..t * freq_bins + low_bins], &frame[..low_bins]);
+        }
+    }
+
+    #[test]
+    fn test_tone_level_matches_across_regions() {
+        // on a bin of both sizes, so neither scallops
+        let freq = 54.0 * SAMPLE_RATE / 512.0;
+        let signal = sine(freq, 0.5, 20_000, SAMPLE_RATE);
+        let mut stft = MultiResolutionStft::new(SAMPLE_RATE, &[8192, 512], &[1000.0]);
+        let (blended, num_windows, freq_bins) = stft.process_windows(&signal, 0.5);
+        let (long, _, _) = SpectrogramProcessor::new(8192).process_windows(&signal, 0.5);
+
+        let peak = |frame: &[f32]| frame.iter().cloned().fold(0.0, f32::max);
+        let t = num_windows / 2;
+        let ratio = peak(&blended[t * freq_bins..(t + 1) * freq_bins]) / peak(&long[..freq_bins]);
+        assert!((20.0 * ratio.log10()).abs() < 0.1, "{} dB", 20.0 * ratio.log10());
+    }
+
+    #[test]
+    fn test_highs_keep_transients_sharp() {
+        let mut signal = vec![0.0f32; 48_000];
+        signal[24_000] = 1.0;
+        let high_bin = (8000.0 / (SAMPLE_RATE / 8192.0)) as usize;
+        let frames_lit = |data: &[f32], freq_bins: usize| {
+            let column: Vec<f32> = data.chunks_exact(freq_bins).map(|frame| frame[high_bin]).collect();
+            let top = column.iter().cloned().fold(0.0, f32::max);
+            column.iter().filter(|&&m| m > top * 0.1).count()
+        };
+
+        let mut stft = MultiResolutionStft::new(SAMPLE_RATE, &[8192, 512], &[1000.0]);
+        let (blended, _, freq_bins) = stft.process_windows(&signal, 0.5);
+        let mut long = SpectrogramProcessor::new(8192);
+        // same 256-sample hop
+        let (single, _, _) = long.process_windows(&signal, 1.0 - 256.0 / 8192.0);
+        // the click lights up ~2 short frames against ~30 long ones at the same hop
+        assert!(frames_lit(&blended, freq_bins) <= 3, "{}", frames_lit(&blended, freq_bins));
+        assert!(frames_lit(&single, 4096) > 10 * frames_lit(&blended, freq_bins));
+    }
+}
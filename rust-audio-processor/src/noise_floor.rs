@@ -0,0 +1,401 @@
+// Per-bin noise floor over time, per-band SNR and "signal present" masks
+// Works on `process_windows` magnitude frames. Floors come back as the mean noise power
+// (bias-corrected), in the same 20*log10(magnitude) dB units the renderer uses, so the
+// renderer can take `display_floor_db` as its black level. An optional weighting curve
+// (A, 468, ...) scales every bin before estimation, so floors, masks and band SNR are all
+// read through it.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::{power_to_db, FLOOR_DB};
+use crate::weighting::Weighting;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FloorMethod {
+    // One floor per bin for the whole file: the given percentile (0-1) of the frame
+    // powers, scaled back to the mean assuming Gaussian noise
+    Percentile(f32),
+    // Martin's minimum statistics: recursively smoothed power, minimum over a sliding
+    // window of frames, bias-compensated. Follows a floor that changes over time.
+    MinimumStatistics { window_frames: usize, smoothing: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandSnr {
+    pub low_hz: f32,
+    pub high_hz: f32,
+    // Mean power of the band and of its floor, both in dB
+    pub signal_db: f32,
+    pub floor_db: f32,
+    pub snr_db: f32,
+    // Fraction of frames where the band's power clears its floor by the margin
+    pub present_fraction: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseFloorAnalysis {
+    pub num_windows: usize,
+    pub freq_bins: usize,
+    // num_windows x freq_bins like the spectrogram
+    pub floor_db: Vec<f32>,
+    // Per-bin average of floor_db over time
+    pub average_floor_db: Vec<f32>,
+    // Bin power above floor + margin (num_windows x freq_bins)
+    pub signal_mask: Vec<bool>,
+    pub bands: Vec<BandSnr>,
+    // 5th percentile of average_floor_db: a black level that keeps the floor just visible
+    pub display_floor_db: f32,
+}
+
+pub struct NoiseFloorEstimator {
+    sample_rate: f32,
+    fft_size: usize,
+    method: FloorMethod,
+    median_bins: usize,
+    margin_db: f32,
+    band_edges_hz: Vec<f32>,
+    weighting: Weighting,
+}
+
+impl NoiseFloorEstimator {
+    pub fn new(sample_rate: f32, fft_size: usize) -> NoiseFloorEstimator {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        NoiseFloorEstimator {
+            sample_rate,
+            fft_size,
+            method: FloorMethod::Percentile(0.1),
+            median_bins: 8,
+            margin_db: 10.0,
+            // Top band is the one that tells music from dither
+            band_edges_hz: vec![20.0, 200.0, 2_000.0, 8_000.0, 16_000.0, sample_rate / 2.0],
+            weighting: Weighting::Z,
+        }
+    }
+
+    pub fn with_method(mut self, method: FloorMethod) -> Self {
+        if let FloorMethod::Percentile(p) = method {
+            assert!(p > 0.0 && p < 1.0, "percentile must be in (0, 1)");
+        }
+        if let FloorMethod::MinimumStatistics { window_frames, smoothing } = method {
+            assert!(window_frames >= 1, "window_frames must be >= 1");
+            assert!((0.0..1.0).contains(&smoothing), "smoothing must be in [0, 1)");
+        }
+        self.method = method;
+        self
+    }
+
+    // Running median over +-bins across frequency, applied to the floor of every frame.
+    // A steady tone is its own minimum over time; the median keeps it out of the floor
+    // as long as its main lobe is narrower than the median window. 0 disables it.
+    pub fn with_median_bins(mut self, bins: usize) -> Self {
+        self.median_bins = bins;
+        self
+    }
+
+    // A bin counts as signal when it is this far above its floor
+    pub fn with_margin_db(mut self, margin_db: f32) -> Self {
+        self.margin_db = margin_db;
+        self
+    }
+
+    // Weighting applied to the spectrum before the floor is estimated (default Z, flat)
+    pub fn with_weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    // Ascending band edges in Hz; consecutive pairs form the bands
+    pub fn with_band_edges(mut self, edges_hz: &[f32]) -> Self {
+        assert!(edges_hz.len() >= 2, "need at least one band");
+        self.band_edges_hz = edges_hz.to_vec();
+        self
+    }
+
+    pub fn analyze(&self, audio_data: &[f32]) -> NoiseFloorAnalysis {
+        let mut processor = SpectrogramProcessor::new(self.fft_size);
+        let (frames, _, freq_bins) = processor.process_windows(audio_data, 0.5);
+        self.analyze_spectrogram(&frames, freq_bins)
+    }
+
+    // `frames` from `process_windows` with this estimator's FFT size and no frequency stride
+    pub fn analyze_spectrogram(&self, frames: &[f32], freq_bins: usize) -> NoiseFloorAnalysis {
+        let num_windows = frames.len().checked_div(freq_bins).unwrap_or(0);
+        let weights = self.weighting.spectral_weights(2 * freq_bins, self.sample_rate);
+        let power: Vec<f32> = frames[..num_windows * freq_bins]
+            .iter()
+            .zip(weights.iter().cycle())
+            .map(|(m, w)| (m * w) * (m * w))
+            .collect();
+
+        let mut floor_power = match self.method {
+            FloorMethod::Percentile(p) => percentile_floor(&power, num_windows, freq_bins, p),
+            FloorMethod::MinimumStatistics { window_frames, smoothing } => {
+                minimum_statistics_floor(&power, num_windows, freq_bins, window_frames, smoothing)
+            }
+        };
+        if self.median_bins > 0 {
+            for row in floor_power.chunks_mut(freq_bins) {
+                median_across_bins(row, self.median_bins);
+            }
+        }
+
+        let floor_db: Vec<f32> = floor_power.iter().map(|&p| power_to_db(p)).collect();
+        let margin = 10f32.powf(self.margin_db / 10.0);
+        let signal_mask: Vec<bool> = power.iter().zip(floor_power.iter()).map(|(&p, &f)| p > f * margin).collect();
+
+        // No frames means no floor estimate, not a 0 dB one
+        let average_floor_db: Vec<f32> = (0..freq_bins)
+            .map(|k| match num_windows {
+                0 => FLOOR_DB,
+                n => (0..n).map(|t| floor_db[t * freq_bins + k]).sum::<f32>() / n as f32,
+            })
+            .collect();
+        let mut sorted = average_floor_db.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let display_floor_db = sorted.get(sorted.len() / 20).cloned().unwrap_or(FLOOR_DB);
+
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let bands = self
+            .band_edges_hz
+            .windows(2)
+            .map(|edge| {
+                let lo = ((edge[0] / bin_hz).ceil() as usize).min(freq_bins);
+                let hi = ((edge[1] / bin_hz).ceil() as usize).clamp(lo, freq_bins);
+                let width = (hi - lo).max(1) as f32;
+                let (mut signal, mut floor, mut present) = (0.0f64, 0.0f64, 0usize);
+                for t in 0..num_windows {
+                    let row = t * freq_bins;
+                    let frame_signal: f32 = power[row + lo..row + hi].iter().sum();
+                    let frame_floor: f32 = floor_power[row + lo..row + hi].iter().sum();
+                    signal += frame_signal as f64;
+                    floor += frame_floor as f64;
+                    if frame_signal > frame_floor * margin {
+                        present += 1;
+                    }
+                }
+                let frames_f = num_windows.max(1) as f64 * width as f64;
+                let signal_db = power_to_db((signal / frames_f) as f32);
+                let floor_db = power_to_db((floor / frames_f) as f32);
+                BandSnr {
+                    low_hz: edge[0],
+                    high_hz: edge[1],
+                    signal_db,
+                    floor_db,
+                    snr_db: signal_db - floor_db,
+                    present_fraction: present as f32 / num_windows.max(1) as f32,
+                }
+            })
+            .collect();
+
+        NoiseFloorAnalysis {
+            num_windows,
+            freq_bins,
+            floor_db,
+            average_floor_db,
+            signal_mask,
+            bands,
+            display_floor_db,
+        }
+    }
+}
+
+fn median_across_bins(row: &mut [f32], half_width: usize) {
+    let source = row.to_vec();
+    let mut neighbourhood = Vec::with_capacity(2 * half_width + 1);
+    for (k, value) in row.iter_mut().enumerate() {
+        let (lo, hi) = (k.saturating_sub(half_width), (k + half_width + 1).min(source.len()));
+        neighbourhood.clear();
+        neighbourhood.extend_from_slice(&source[lo..hi]);
+        neighbourhood.sort_by(|a, b| a.total_cmp(b));
+        *value = neighbourhood[neighbourhood.len() / 2];
+    }
+}
+
+// Per-frame bin power of Gaussian noise is exponential, whose p-quantile is -ln(1 - p)
+// times the mean; dividing by that turns the percentile into a mean-power estimate
+fn percentile_floor(power: &[f32], num_windows: usize, freq_bins: usize, p: f32) -> Vec<f32> {
+    let correction = -(1.0 - p).ln();
+    let mut column = Vec::with_capacity(num_windows);
+    let per_bin: Vec<f32> = (0..freq_bins)
+        .map(|k| {
+            column.clear();
+            column.extend((0..num_windows).map(|t| power[t * freq_bins + k]));
+            column.sort_by(|a, b| a.total_cmp(b));
+            let index = ((column.len() as f32 * p) as usize).min(column.len().saturating_sub(1));
+            column.get(index).map(|v| v / correction).unwrap_or(0.0)
+        })
+        .collect();
+    (0..num_windows).flat_map(|_| per_bin.iter().cloned()).collect()
+}
+
+// Martin (2001): the minimum of D smoothed periodogram values underestimates the mean
+// by B = 1 + (D - 1) * 2 / Q', with Q' = (Q - M(D)) / (1 - M(D)) and Q = 2 (1 + a) / (1 - a)
+// the equivalent degrees of freedom of the smoothed power
+fn minimum_statistics_floor(power: &[f32], num_windows: usize, freq_bins: usize, window_frames: usize, smoothing: f32) -> Vec<f32> {
+    let d = window_frames as f32;
+    let q = 2.0 * (1.0 + smoothing) / (1.0 - smoothing);
+    let m = martin_m(d);
+    let q_tilde = ((q - m) / (1.0 - m)).max(1.0);
+    let bias = 1.0 + (d - 1.0) * 2.0 / q_tilde;
+
+    let mut floor = vec![0.0f32; power.len()];
+    for k in 0..freq_bins {
+        let mut smoothed = if num_windows > 0 { power[k] } else { 0.0 };
+        let mut history: Vec<f32> = Vec::with_capacity(window_frames);
+        for t in 0..num_windows {
+            smoothed = smoothing * smoothed + (1.0 - smoothing) * power[t * freq_bins + k];
+            if history.len() == window_frames {
+                history.remove(0);
+            }
+            history.push(smoothed);
+            let minimum = history.iter().cloned().fold(f32::INFINITY, f32::min);
+            floor[t * freq_bins + k] = minimum * bias;
+        }
+    }
+    floor
+}
+
+// M(D) from Martin's table, linearly interpolated
+fn martin_m(d: f32) -> f32 {
+    const TABLE: [(f32, f32); 14] = [
+        (1.0, 0.0),
+        (2.0, 0.26),
+        (5.0, 0.48),
+        (8.0, 0.58),
+        (10.0, 0.61),
+        (15.0, 0.668),
+        (20.0, 0.705),
+        (30.0, 0.762),
+        (40.0, 0.8),
+        (60.0, 0.841),
+        (80.0, 0.865),
+        (120.0, 0.89),
+        (140.0, 0.9),
+        (160.0, 0.91),
+    ];
+    if d >= TABLE[TABLE.len() - 1].0 {
+        return TABLE[TABLE.len() - 1].1;
+    }
+    for pair in TABLE.windows(2) {
+        let ((d0, m0), (d1, m1)) = (pair[0], pair[1]);
+        if d <= d1 {
+            return m0 + (m1 - m0) * (d - d0) / (d1 - d0);
+        }
+    }
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{brickwall_lowpass, mix, white_noise};
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+    const FFT_SIZE: usize = 1024;
+
+    fn mean_power_db(frames: &[f32]) -> f32 {
+        power_to_db(frames.iter().map(|m| m * m).sum::<f32>() / frames.len() as f32)
+    }
+
+    #[test]
+    fn test_white_noise_floor_is_unbiased() {
+        let noise = white_noise(1 << 17, 0.01, 1);
+        let (frames, _, freq_bins) = SpectrogramProcessor::new(FFT_SIZE).process_windows(&noise, 0.5);
+        let truth = mean_power_db(&frames);
+
+        for method in [
+            FloorMethod::Percentile(0.1),
+            FloorMethod::MinimumStatistics {
+                window_frames: 60,
+                smoothing: 0.85,
+            },
+        ] {
+            let analysis = NoiseFloorEstimator::new(SAMPLE_RATE, FFT_SIZE)
+                .with_method(method)
+                .analyze_spectrogram(&frames, freq_bins);
+            let inner = &analysis.average_floor_db[8..freq_bins - 8];
+            let average = inner.iter().sum::<f32>() / inner.len() as f32;
+            assert!((average - truth).abs() < 1.5, "{:?}: {} vs {}", method, average, truth);
+            // Pure noise: the mask stays almost empty
+            let present = analysis.signal_mask.iter().filter(|&&m| m).count() as f32 / analysis.signal_mask.len() as f32;
+            assert!(present < 0.01, "{:?}: {}", method, present);
+        }
+    }
+
+    #[test]
+    fn test_tone_mask_and_band_snr() {
+        // Loud band-limited "music" under 8 kHz over a -80 dB dither-like floor
+        let music = brickwall_lowpass(&white_noise(1 << 16, 0.3, 2), 8_000.0, SAMPLE_RATE);
+        let tone: Vec<f32> = (0..1 << 16).map(|i| 0.1 * (2.0 * PI * 3_000.0 * i as f32 / SAMPLE_RATE).sin()).collect();
+        let signal = mix(&mix(&music, &tone), &white_noise(1 << 16, 1e-4, 3));
+        let analysis = NoiseFloorEstimator::new(SAMPLE_RATE, FFT_SIZE).analyze(&signal);
+
+        let top = analysis.bands.last().unwrap();
+        assert_eq!(top.low_hz, 16_000.0);
+        assert!(top.snr_db.abs() < 2.0, "{:?}", top);
+        assert!(top.present_fraction < 0.05);
+        // 2-8 kHz is stationary noise too, so its SNR is small even though it is loud:
+        // the floor tracks it. What separates it from the top band is its level.
+        let mid = &analysis.bands[2];
+        assert!(mid.floor_db > top.floor_db + 50.0, "{:?} {:?}", mid, top);
+
+        let tone_bin = (3_000.0 / (SAMPLE_RATE / FFT_SIZE as f32)).round() as usize;
+        let tone_present = (0..analysis.num_windows)
+            .filter(|t| analysis.signal_mask[t * analysis.freq_bins + tone_bin])
+            .count();
+        assert!(tone_present as f32 > 0.9 * analysis.num_windows as f32);
+        assert!(analysis.display_floor_db < top.floor_db + 3.0);
+    }
+
+    #[test]
+    fn test_weighting_shifts_floor_by_the_curve() {
+        // Without the median each bin's floor scales exactly with its weight
+        let noise = white_noise(1 << 16, 0.01, 6);
+        let estimate = |weighting| {
+            NoiseFloorEstimator::new(SAMPLE_RATE, FFT_SIZE)
+                .with_median_bins(0)
+                .with_weighting(weighting)
+                .analyze(&noise)
+                .average_floor_db
+        };
+        let flat = estimate(Weighting::Z);
+        let bin_hz = SAMPLE_RATE as f64 / FFT_SIZE as f64;
+        for weighting in [Weighting::A, Weighting::Itu468] {
+            let weighted = estimate(weighting);
+            for k in 1..flat.len() {
+                let expected = weighting.gain_db(k as f64 * bin_hz) as f32;
+                assert!((weighted[k] - flat[k] - expected).abs() < 0.01, "{:?} bin {}", weighting, k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimum_statistics_tracks_level_change() {
+        let quiet = white_noise(1 << 16, 0.001, 4);
+        let loud = white_noise(1 << 16, 0.1, 5);
+        let signal = [quiet, loud].concat();
+        let (frames, num_windows, freq_bins) = SpectrogramProcessor::new(FFT_SIZE).process_windows(&signal, 0.5);
+        let analysis = NoiseFloorEstimator::new(SAMPLE_RATE, FFT_SIZE)
+            .with_method(FloorMethod::MinimumStatistics {
+                window_frames: 30,
+                smoothing: 0.8,
+            })
+            .analyze_spectrogram(&frames, freq_bins);
+
+        let frame_floor = |t: usize| analysis.floor_db[t * freq_bins + 100];
+        let early = frame_floor(num_windows / 4);
+        let late = frame_floor(num_windows - 5);
+        assert!((late - early - 40.0).abs() < 4.0, "{} {}", early, late);
+    }
+
+    #[test]
+    fn test_empty_input_reads_floor_db() {
+        let analysis = NoiseFloorEstimator::new(SAMPLE_RATE, FFT_SIZE).analyze(&[]);
+        assert_eq!(analysis.num_windows, 0);
+        assert!(analysis.average_floor_db.iter().all(|&db| db == FLOOR_DB));
+        assert_eq!(analysis.display_floor_db, FLOOR_DB);
+    }
+}
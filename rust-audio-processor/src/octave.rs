@@ -0,0 +1,298 @@
+// Octave and fractional-octave band levels from FFT frames
+// Band edges follow IEC 61260-1 base-10 midband frequencies. Each bin's power is shared
+// between bands by how much of the bin's width falls inside them, so the bands always
+// add up to the full spectrum. Bands narrower than a bin get a share but not detail;
+// `resolved` says whether the FFT is fine enough for the band.
+use crate::audio_processor::{frame_starts, hop_size};
+use crate::db::{power_to_db, FLOOR_DB};
+use crate::fft::{fft_with_cache, TwiddleCache};
+use crate::utils::Complex;
+use crate::window::{equivalent_noise_bandwidth, generate_window, WindowKind};
+
+// Octave ratio of the base-10 system
+const G: f64 = 1.995_262_314_968_879_5; // 10^(3/10)
+const REFERENCE_HZ: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandFraction {
+    Octave,
+    Third,
+    Sixth,
+    Twelfth,
+}
+
+impl BandFraction {
+    // b in "1/b octave"
+    pub fn bands_per_octave(&self) -> usize {
+        match self {
+            BandFraction::Octave => 1,
+            BandFraction::Third => 3,
+            BandFraction::Sixth => 6,
+            BandFraction::Twelfth => 12,
+        }
+    }
+
+    // Exact midband frequency of band x; odd b has a band at 1 kHz, even b straddles it
+    fn midband_hz(&self, x: i32) -> f64 {
+        let b = self.bands_per_octave() as f64;
+        if self.bands_per_octave() % 2 == 1 {
+            REFERENCE_HZ * G.powf(x as f64 / b)
+        } else {
+            REFERENCE_HZ * G.powf((2 * x + 1) as f64 / (2.0 * b))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OctaveBand {
+    pub center_hz: f32,
+    pub lower_hz: f32,
+    pub upper_hz: f32,
+    // At least one noise bandwidth of the window fits in the band
+    pub resolved: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OctaveBandAnalysis {
+    pub bands: Vec<OctaveBand>,
+    pub num_windows: usize,
+    // num_windows x bands, dBFS (a full-scale sine in the band reads 0 dB)
+    pub levels_db: Vec<f32>,
+    // Power mean over all windows
+    pub overall_db: Vec<f32>,
+}
+
+pub struct OctaveBandAnalyzer {
+    sample_rate: f32,
+    fft_size: usize,
+    fraction: BandFraction,
+    window_kind: WindowKind,
+    min_hz: f32,
+    max_hz: f32,
+    window: Vec<f32>,
+    twiddle_cache: TwiddleCache,
+    bands: Vec<OctaveBand>,
+    // Per band: (bin, weight) pairs
+    weights: Vec<Vec<(usize, f32)>>,
+    // |X|^2 to mean square
+    power_scale: f32,
+}
+
+impl OctaveBandAnalyzer {
+    // Bands between 20 Hz and 20 kHz (or Nyquist), Hann window
+    pub fn new(sample_rate: f32, fft_size: usize, fraction: BandFraction) -> OctaveBandAnalyzer {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        let mut analyzer = OctaveBandAnalyzer {
+            sample_rate,
+            fft_size,
+            fraction,
+            window_kind: WindowKind::Hann,
+            min_hz: 20.0,
+            max_hz: 20_000.0,
+            window: Vec::new(),
+            twiddle_cache: TwiddleCache::new(fft_size),
+            bands: Vec::new(),
+            weights: Vec::new(),
+            power_scale: 0.0,
+        };
+        analyzer.configure();
+        analyzer
+    }
+
+    // Keep the bands whose range overlaps [min_hz, max_hz]; max is capped at Nyquist
+    pub fn with_range(mut self, min_hz: f32, max_hz: f32) -> Self {
+        assert!(min_hz > 0.0 && max_hz > min_hz, "need 0 < min_hz < max_hz");
+        self.min_hz = min_hz;
+        self.max_hz = max_hz;
+        self.configure();
+        self
+    }
+
+    pub fn with_window(mut self, window_kind: WindowKind) -> Self {
+        self.window_kind = window_kind;
+        self.configure();
+        self
+    }
+
+    pub fn bands(&self) -> &[OctaveBand] {
+        &self.bands
+    }
+
+    // Levels of every window at the given overlap, plus the overall level per band
+    pub fn analyze(&self, audio_data: &[f32], overlap: f32) -> OctaveBandAnalysis {
+        let starts = frame_starts(audio_data.len(), self.fft_size, hop_size(self.fft_size, overlap));
+        let num_windows = starts.len();
+        let mut buffer = vec![Complex::new(0.0, 0.0); self.fft_size];
+        let mut levels_db = Vec::with_capacity(num_windows * self.bands.len());
+        let mut total = vec![0.0f64; self.bands.len()];
+
+        for start in starts {
+            let power = self.band_power(&audio_data[start..start + self.fft_size], &mut buffer);
+            for (sum, &p) in total.iter_mut().zip(power.iter()) {
+                *sum += p as f64;
+            }
+            levels_db.extend(power.iter().map(|&p| mean_square_db(p)));
+        }
+
+        let overall_db = total
+            .iter()
+            .map(|&sum| if num_windows == 0 { FLOOR_DB } else { mean_square_db((sum / num_windows as f64) as f32) })
+            .collect();
+        OctaveBandAnalysis {
+            bands: self.bands.clone(),
+            num_windows,
+            levels_db,
+            overall_db,
+        }
+    }
+
+    // Mean square per band for one frame of fft_size samples
+    fn band_power(&self, frame: &[f32], buffer: &mut [Complex]) -> Vec<f32> {
+        for ((slot, &s), &w) in buffer.iter_mut().zip(frame.iter()).zip(self.window.iter()) {
+            *slot = Complex::new(s * w, 0.0);
+        }
+        fft_with_cache(buffer, &self.twiddle_cache);
+        self.weights
+            .iter()
+            .map(|bins| {
+                let sum: f32 = bins
+                    .iter()
+                    .map(|&(k, weight)| weight * (buffer[k].real * buffer[k].real + buffer[k].imag * buffer[k].imag))
+                    .sum();
+                sum * self.power_scale
+            })
+            .collect()
+    }
+
+    // Rebuild bands, window and bin weights after a setting changed
+    fn configure(&mut self) {
+        let (fraction, min_hz) = (self.fraction, self.min_hz);
+        let nyquist = self.sample_rate / 2.0;
+        let max_hz = self.max_hz.min(nyquist);
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        self.window = generate_window(self.window_kind, self.fft_size);
+        let window_power: f32 = self.window.iter().map(|w| w * w).sum();
+        // One-sided: a tone of amplitude A sums to A^2 / 2
+        self.power_scale = 2.0 / (self.fft_size as f32 * window_power);
+        let noise_bandwidth = equivalent_noise_bandwidth(&self.window) * bin_hz;
+
+        let half = 1.0 / (2.0 * fraction.bands_per_octave() as f64);
+        let lowest = (fraction.bands_per_octave() as f64 * (min_hz as f64 / REFERENCE_HZ).log(G)).floor() as i32 - 1;
+        self.bands = (lowest..)
+            .map(|x| fraction.midband_hz(x))
+            .take_while(|&fm| fm * G.powf(-half) < max_hz as f64)
+            .filter(|&fm| fm * G.powf(half) > min_hz as f64 && fm * G.powf(-half) < nyquist as f64)
+            .map(|fm| {
+                let lower_hz = (fm * G.powf(-half)) as f32;
+                let upper_hz = ((fm * G.powf(half)) as f32).min(nyquist);
+                OctaveBand {
+                    center_hz: fm as f32,
+                    lower_hz,
+                    upper_hz,
+                    resolved: upper_hz - lower_hz >= noise_bandwidth,
+                }
+            })
+            .collect();
+
+        // Bin k spans (k - 0.5, k + 0.5) * bin_hz
+        self.weights = self
+            .bands
+            .iter()
+            .map(|band| {
+                let first = ((band.lower_hz / bin_hz - 0.5).floor().max(0.0)) as usize;
+                let last = ((band.upper_hz / bin_hz + 0.5).ceil() as usize).min(self.fft_size / 2);
+                (first..=last)
+                    .filter_map(|k| {
+                        let (lo, hi) = ((k as f32 - 0.5) * bin_hz, (k as f32 + 0.5) * bin_hz);
+                        let covered = (hi.min(band.upper_hz) - lo.max(band.lower_hz)).max(0.0) / bin_hz;
+                        (covered > 0.0).then_some((k, covered))
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+// Mean square to dB relative to a full-scale sine (mean square 0.5)
+fn mean_square_db(mean_square: f32) -> f32 {
+    power_to_db(2.0 * mean_square)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{sine, white_noise};
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_band_layout() {
+        let octaves = OctaveBandAnalyzer::new(SAMPLE_RATE, 8192, BandFraction::Octave);
+        let centres: Vec<f32> = octaves.bands().iter().map(|b| b.center_hz).collect();
+        // 15.85 Hz (nominal 16) up to 15.85 kHz (nominal 16k)
+        assert_eq!(centres.len(), 11);
+        assert!((centres[6] - 1000.0).abs() < 1e-3);
+        assert!((centres[7] - 1995.26).abs() < 0.01);
+
+        for fraction in [BandFraction::Third, BandFraction::Sixth, BandFraction::Twelfth] {
+            let analyzer = OctaveBandAnalyzer::new(SAMPLE_RATE, 8192, fraction);
+            let bands = analyzer.bands();
+            let b = fraction.bands_per_octave() as f32;
+            for pair in bands.windows(2) {
+                assert!((pair[0].upper_hz - pair[1].lower_hz).abs() < 1e-2);
+                assert!((pair[1].center_hz / pair[0].center_hz - 2f32.powf(1.0 / b)).abs() < 2e-3);
+            }
+            // 1/6 and 1/12 octave bands straddle 1 kHz instead of centring on it
+            let has_1k = bands.iter().any(|band| (band.center_hz - 1000.0).abs() < 0.01);
+            assert_eq!(has_1k, fraction == BandFraction::Third);
+        }
+        // 1/12 octave at 20 Hz is ~1.2 Hz wide, well under a 5.9 Hz bin
+        let twelfths = OctaveBandAnalyzer::new(SAMPLE_RATE, 8192, BandFraction::Twelfth);
+        assert!(!twelfths.bands()[0].resolved);
+        assert!(twelfths.bands().last().unwrap().resolved);
+    }
+
+    #[test]
+    fn test_tone_lands_in_its_band() {
+        let analyzer = OctaveBandAnalyzer::new(SAMPLE_RATE, 8192, BandFraction::Third);
+        let analysis = analyzer.analyze(&sine(1000.0, 0.5, 48_000, SAMPLE_RATE), 0.5);
+        let index = analysis.bands.iter().position(|b| (b.center_hz - 1000.0).abs() < 0.01).unwrap();
+        assert!((analysis.overall_db[index] + 6.02).abs() < 0.1, "{}", analysis.overall_db[index]);
+        assert!(analysis.overall_db[index - 1] < -60.0);
+        assert!(analysis.overall_db[index + 1] < -60.0);
+        assert_eq!(analysis.levels_db.len(), analysis.num_windows * analysis.bands.len());
+    }
+
+    #[test]
+    fn test_white_noise_rises_3_db_per_octave() {
+        let noise = white_noise(1 << 20, 0.5, 31);
+        let analysis = OctaveBandAnalyzer::new(SAMPLE_RATE, 4096, BandFraction::Octave).analyze(&noise, 0.5);
+        // Above 250 Hz the bands are wide enough that the estimate is steady
+        let start = analysis.bands.iter().position(|b| b.center_hz > 240.0).unwrap();
+        for pair in analysis.overall_db[start..analysis.bands.len() - 1].windows(2) {
+            assert!((pair[1] - pair[0] - 3.01).abs() < 0.3, "{:?}", pair);
+        }
+        // The bands add up to the total: noise of +-a has a mean square of a^2 / 3,
+        // and 11.2 Hz - 22.4 kHz covers all but the ends of the spectrum
+        let total: f32 = analysis.overall_db.iter().map(|db| 10f32.powf(db / 10.0) / 2.0).sum();
+        assert!((10.0 * (total / (0.25 / 3.0)).log10()).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_levels_follow_time() {
+        let mut signal = sine(500.0, 0.5, 24_000, SAMPLE_RATE);
+        signal.extend(sine(4000.0, 0.5, 24_000, SAMPLE_RATE));
+        let analysis = OctaveBandAnalyzer::new(SAMPLE_RATE, 2048, BandFraction::Octave).analyze(&signal, 0.0);
+        let bands = analysis.bands.len();
+        let band_500 = analysis.bands.iter().position(|b| (b.center_hz - 501.19).abs() < 0.1).unwrap();
+        let band_4k = analysis.bands.iter().position(|b| (b.center_hz - 3981.07).abs() < 0.1).unwrap();
+        let level = |window: usize, band: usize| analysis.levels_db[window * bands + band];
+        assert!(level(1, band_500) > level(1, band_4k) + 40.0);
+        let last = analysis.num_windows - 1;
+        assert!(level(last, band_4k) > level(last, band_500) + 40.0);
+    }
+}
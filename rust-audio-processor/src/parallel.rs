@@ -0,0 +1,83 @@
+// Multithreaded spectrogram batches (native, `rayon` feature)
+// Workers share the processor's twiddle and window tables read-only and each owns a scratch buffer.
+use crate::audio_processor::{transform_frame, SpectrogramProcessor};
+use crate::utils::Complex;
+use rayon::prelude::*;
+
+impl SpectrogramProcessor {
+    // Same output as `process_windows` (including strides), frames split across the rayon pool
+    pub fn process_windows_parallel(&self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, usize, usize) {
+        let fft_size = self.fft_size();
+        let freq_bins = fft_size / 2;
+        let freq_stride = self.freq_stride();
+
+        if audio_data.len() < fft_size {
+            return (Vec::new(), 0, freq_bins);
+        }
+
+        let window_starts: Vec<usize> = self.window_starts(audio_data.len(), overlap).collect();
+        let num_windows = window_starts.len();
+        let reduced_bins = freq_bins.div_ceil(freq_stride);
+
+        let window = self.window();
+        let twiddle_cache = self.twiddle_cache();
+        let mut result = vec![0.0f32; num_windows * reduced_bins];
+
+        result
+            .par_chunks_mut(reduced_bins)
+            .zip(window_starts.par_iter())
+            .for_each_init(
+                || vec![Complex::new(0.0, 0.0); fft_size],
+                |buffer, (row, &start)| {
+                    transform_frame(buffer, &audio_data[start..start + fft_size], window, twiddle_cache);
+                    for (slot, bin) in row.iter_mut().zip(buffer[0..freq_bins].iter().step_by(freq_stride)) {
+                        *slot = bin.magnitude();
+                    }
+                },
+            );
+
+        (result, num_windows, reduced_bins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_signal(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / 8000.0;
+                (2.0 * std::f32::consts::PI * 440.0 * t).sin() + 0.25 * (2.0 * std::f32::consts::PI * 3100.0 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let signal = test_signal(20_000);
+        let mut processor = SpectrogramProcessor::new(512);
+
+        let sequential = processor.process_windows(&signal, 0.75);
+        let parallel = processor.process_windows_parallel(&signal, 0.75);
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential_with_strides() {
+        let signal = test_signal(9_000);
+        let mut processor = SpectrogramProcessor::new(256).with_strides(3, 2);
+
+        let sequential = processor.process_windows(&signal, 0.5);
+        let parallel = processor.process_windows_parallel(&signal, 0.5);
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn test_parallel_short_input() {
+        let processor = SpectrogramProcessor::new(64);
+        let (data, num_windows, freq_bins) = processor.process_windows_parallel(&[0.0; 10], 0.5);
+        assert!(data.is_empty());
+        assert_eq!((num_windows, freq_bins), (0, 32));
+    }
+}
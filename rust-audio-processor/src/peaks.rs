@@ -0,0 +1,302 @@
+// Spectral peak picking on `process_window` output with sub-bin refinement
+// Answers "which frequencies are in this frame" for cursor readouts, hum detection and
+// test-tone measurement. Amplitudes are corrected for the Hann window, so a full-scale
+// sine reads 0 dB.
+use crate::db::amplitude_to_db;
+use crate::utils::Complex;
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PeakInterpolation {
+    // Bin centre, no refinement
+    None,
+    // Parabola through the three linear magnitudes around the peak
+    Parabolic,
+    // Parabola through the log magnitudes (exact for a Gaussian main lobe, close for Hann)
+    Gaussian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectralPeak {
+    pub bin: usize,
+    pub frequency_hz: f32,
+    // Estimated sinusoid amplitude (1.0 = full scale) and the same in dB
+    pub amplitude: f32,
+    pub amplitude_db: f32,
+    // Height above the higher of the two valleys separating it from taller peaks
+    pub prominence_db: f32,
+}
+
+pub struct PeakDetector {
+    sample_rate: f32,
+    fft_size: usize,
+    threshold_db: f32,
+    min_prominence_db: f32,
+    min_distance_hz: f32,
+    max_peaks: Option<usize>,
+    interpolation: PeakInterpolation,
+}
+
+impl PeakDetector {
+    pub fn new(sample_rate: f32, fft_size: usize) -> PeakDetector {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        PeakDetector {
+            sample_rate,
+            fft_size,
+            threshold_db: -100.0,
+            min_prominence_db: 6.0,
+            min_distance_hz: 0.0,
+            max_peaks: None,
+            interpolation: PeakInterpolation::Gaussian,
+        }
+    }
+
+    // Peaks below this amplitude (dB re full-scale sine) are ignored. The -100 dB default
+    // only keeps f32 round-off ripple out; raise it to the material's noise floor.
+    pub fn with_threshold_db(mut self, threshold_db: f32) -> Self {
+        self.threshold_db = threshold_db;
+        self
+    }
+
+    pub fn with_min_prominence_db(mut self, min_prominence_db: f32) -> Self {
+        self.min_prominence_db = min_prominence_db;
+        self
+    }
+
+    // Within this distance only the largest peak survives
+    pub fn with_min_distance_hz(mut self, min_distance_hz: f32) -> Self {
+        self.min_distance_hz = min_distance_hz;
+        self
+    }
+
+    pub fn with_max_peaks(mut self, max_peaks: usize) -> Self {
+        self.max_peaks = Some(max_peaks);
+        self
+    }
+
+    pub fn with_interpolation(mut self, interpolation: PeakInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    fn bin_hz(&self) -> f32 {
+        self.sample_rate / self.fft_size as f32
+    }
+
+    // Hann coherent gain is 1/2, and a real sine splits its energy over +-f
+    fn amplitude_of(&self, magnitude: f32) -> f32 {
+        magnitude * 4.0 / self.fft_size as f32
+    }
+
+    // Peaks of a magnitude frame from `process_window`, sorted by frequency
+    pub fn find_peaks(&self, magnitudes: &[f32]) -> Vec<SpectralPeak> {
+        self.select(magnitudes)
+            .into_iter()
+            .map(|(bin, prominence_db)| {
+                let (offset, magnitude) = interpolate(magnitudes, bin, self.interpolation);
+                self.peak(bin, offset, magnitude, prominence_db)
+            })
+            .collect()
+    }
+
+    // Phase-vocoder refinement: `current` and `previous` are `process_window_complex`
+    // frames `hop_size` samples apart. The phase advance of each peak bin pins the
+    // frequency far tighter than any magnitude fit (the offset must be within
+    // fft_size / (2 * hop_size) bins of the bin centre, so keep the hop <= fft_size / 4).
+    pub fn find_peaks_phase_vocoder(&self, current: &[Complex], previous: &[Complex], hop_size: usize) -> Vec<SpectralPeak> {
+        assert_eq!(current.len(), previous.len(), "frames must have the same length");
+        let magnitudes: Vec<f32> = current.iter().map(|c| c.magnitude()).collect();
+        let hop = hop_size as f32;
+        let n = self.fft_size as f32;
+
+        self.select(&magnitudes)
+            .into_iter()
+            .map(|(bin, prominence_db)| {
+                let expected = 2.0 * PI * bin as f32 * hop / n;
+                let deviation = wrap_phase(current[bin].phase() - previous[bin].phase() - expected);
+                let offset = deviation * n / (2.0 * PI * hop);
+                let (_, magnitude) = interpolate(&magnitudes, bin, PeakInterpolation::Gaussian);
+                self.peak(bin, offset, magnitude, prominence_db)
+            })
+            .collect()
+    }
+
+    fn peak(&self, bin: usize, offset: f32, magnitude: f32, prominence_db: f32) -> SpectralPeak {
+        let amplitude = self.amplitude_of(magnitude);
+        SpectralPeak {
+            bin,
+            frequency_hz: (bin as f32 + offset) * self.bin_hz(),
+            amplitude,
+            amplitude_db: amplitude_to_db(amplitude),
+            prominence_db,
+        }
+    }
+
+    // Local maxima passing threshold and prominence, thinned by distance (largest first),
+    // capped at max_peaks, returned in bin order with their prominence
+    fn select(&self, magnitudes: &[f32]) -> Vec<(usize, f32)> {
+        let db: Vec<f32> = magnitudes.iter().map(|&m| amplitude_to_db(self.amplitude_of(m))).collect();
+        let mut candidates: Vec<(usize, f32)> = (1..db.len().saturating_sub(1))
+            .filter(|&k| db[k] > db[k - 1] && db[k] >= db[k + 1] && db[k] >= self.threshold_db)
+            .map(|k| (k, prominence(&db, k)))
+            .filter(|&(_, p)| p >= self.min_prominence_db)
+            .collect();
+
+        candidates.sort_by(|a, b| db[b.0].total_cmp(&db[a.0]));
+        let min_distance_bins = self.min_distance_hz / self.bin_hz();
+        let mut kept: Vec<(usize, f32)> = Vec::new();
+        for candidate in candidates {
+            if kept.iter().all(|&(k, _)| (k as f32 - candidate.0 as f32).abs() >= min_distance_bins) {
+                kept.push(candidate);
+            }
+            if self.max_peaks.is_some_and(|max| kept.len() >= max) {
+                break;
+            }
+        }
+        kept.sort_by_key(|&(k, _)| k);
+        kept
+    }
+}
+
+// Walk out each side until a higher bin (or the edge); the higher of the two minima is
+// the peak's base
+fn prominence(db: &[f32], k: usize) -> f32 {
+    let mut left_min = db[k];
+    for &v in db[..k].iter().rev() {
+        if v > db[k] {
+            break;
+        }
+        left_min = left_min.min(v);
+    }
+    let mut right_min = db[k];
+    for &v in &db[k + 1..] {
+        if v > db[k] {
+            break;
+        }
+        right_min = right_min.min(v);
+    }
+    db[k] - left_min.max(right_min)
+}
+
+// (offset in bins, refined magnitude) from the three bins around `k`
+pub(crate) fn interpolate(magnitudes: &[f32], k: usize, interpolation: PeakInterpolation) -> (f32, f32) {
+    let (a, b, c) = (magnitudes[k - 1], magnitudes[k], magnitudes[k + 1]);
+    match interpolation {
+        PeakInterpolation::None => (0.0, b),
+        PeakInterpolation::Parabolic => parabola(a, b, c),
+        PeakInterpolation::Gaussian => {
+            let ln = |m: f32| m.max(1e-30).ln();
+            let (offset, value) = parabola(ln(a), ln(b), ln(c));
+            (offset, value.exp())
+        }
+    }
+}
+
+// Vertex of the parabola through (-1, a), (0, b), (1, c)
+fn parabola(a: f32, b: f32, c: f32) -> (f32, f32) {
+    let denominator = a - 2.0 * b + c;
+    if denominator == 0.0 {
+        return (0.0, b);
+    }
+    let offset = (0.5 * (a - c) / denominator).clamp(-0.5, 0.5);
+    (offset, b - 0.25 * (a - c) * offset)
+}
+
+fn wrap_phase(phase: f32) -> f32 {
+    phase - 2.0 * PI * ((phase + PI) / (2.0 * PI)).floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::SpectrogramProcessor;
+    use crate::test_signals::white_noise;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+    const FFT_SIZE: usize = 4096;
+
+    fn tones(parts: &[(f32, f32)], len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                parts
+                    .iter()
+                    .map(|&(freq, amp)| amp * (2.0 * PI * freq * i as f32 / SAMPLE_RATE).sin())
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interpolation_accuracy() {
+        let signal = tones(&[(1000.3, 0.5), (3333.7, 0.1)], FFT_SIZE);
+        let magnitudes = SpectrogramProcessor::new(FFT_SIZE).process_window(&signal);
+
+        let detector = PeakDetector::new(SAMPLE_RATE, FFT_SIZE).with_threshold_db(-60.0);
+        let gaussian = detector.find_peaks(&magnitudes);
+        assert_eq!(gaussian.len(), 2);
+        assert!((gaussian[0].frequency_hz - 1000.3).abs() < 0.5, "{}", gaussian[0].frequency_hz);
+        assert!((gaussian[1].frequency_hz - 3333.7).abs() < 0.5, "{}", gaussian[1].frequency_hz);
+        assert!((gaussian[0].amplitude_db + 6.02).abs() < 0.2, "{}", gaussian[0].amplitude_db);
+        assert!((gaussian[1].amplitude_db + 20.0).abs() < 0.2, "{}", gaussian[1].amplitude_db);
+
+        let parabolic = PeakDetector::new(SAMPLE_RATE, FFT_SIZE)
+            .with_threshold_db(-60.0)
+            .with_interpolation(PeakInterpolation::Parabolic)
+            .find_peaks(&magnitudes);
+        let centre = PeakDetector::new(SAMPLE_RATE, FFT_SIZE)
+            .with_threshold_db(-60.0)
+            .with_interpolation(PeakInterpolation::None)
+            .find_peaks(&magnitudes);
+        // every refinement beats the raw bin centre
+        for (p, c) in parabolic.iter().zip(centre.iter()) {
+            let truth = if p.frequency_hz < 2000.0 { 1000.3 } else { 3333.7 };
+            assert!((p.frequency_hz - truth).abs() < (c.frequency_hz - truth).abs());
+            assert!((p.frequency_hz - truth).abs() < 2.0);
+        }
+    }
+
+    #[test]
+    fn test_phase_vocoder_frequency() {
+        let hop = FFT_SIZE / 4;
+        let signal = tones(&[(1234.56, 0.3)], FFT_SIZE + hop);
+        let mut processor = SpectrogramProcessor::new(FFT_SIZE);
+        let previous = processor.process_window_complex(&signal[..FFT_SIZE]);
+        let current = processor.process_window_complex(&signal[hop..hop + FFT_SIZE]);
+
+        let peaks = PeakDetector::new(SAMPLE_RATE, FFT_SIZE)
+            .with_threshold_db(-60.0)
+            .find_peaks_phase_vocoder(&current, &previous, hop);
+        assert_eq!(peaks.len(), 1, "{:?}", peaks);
+        assert!((peaks[0].frequency_hz - 1234.56).abs() < 0.05, "{}", peaks[0].frequency_hz);
+        assert!((peaks[0].amplitude - 0.3).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_threshold_prominence_and_distance() {
+        // 50 Hz hum with harmonics over noise, plus a weak tone 30 Hz away from the 150 Hz one
+        let mut signal = tones(&[(50.0, 0.2), (100.0, 0.1), (150.0, 0.05), (180.0, 0.01)], FFT_SIZE * 2);
+        for (s, n) in signal.iter_mut().zip(white_noise(FFT_SIZE * 2, 0.001, 5)) {
+            *s += n;
+        }
+        let magnitudes = SpectrogramProcessor::new(FFT_SIZE * 2).process_window(&signal);
+
+        let detector = PeakDetector::new(SAMPLE_RATE, FFT_SIZE * 2).with_threshold_db(-50.0);
+        let all: Vec<f32> = detector.find_peaks(&magnitudes).iter().map(|p| p.frequency_hz).collect();
+        assert_eq!(all.len(), 4, "{:?}", all);
+
+        let spaced = PeakDetector::new(SAMPLE_RATE, FFT_SIZE * 2)
+            .with_threshold_db(-50.0)
+            .with_min_distance_hz(40.0)
+            .find_peaks(&magnitudes);
+        let freqs: Vec<f32> = spaced.iter().map(|p| p.frequency_hz.round()).collect();
+        assert_eq!(freqs, vec![50.0, 100.0, 150.0]);
+
+        let strongest = PeakDetector::new(SAMPLE_RATE, FFT_SIZE * 2).with_max_peaks(1).find_peaks(&magnitudes);
+        assert_eq!(strongest.len(), 1);
+        assert!((strongest[0].frequency_hz - 50.0).abs() < 1.0);
+        assert!(strongest[0].prominence_db > 40.0);
+    }
+}
@@ -0,0 +1,141 @@
+// Progress reporting and cooperative cancellation for long spectrogram runs
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Shared flag checked between frames. Clones observe the same flag, so one
+// copy can be handed to the worker and another kept by whoever may cancel.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessError {
+    // Stopped between frames; carries the windows finished so far (window-major, like `process_windows`)
+    Cancelled {
+        data: Vec<f32>,
+        num_windows: usize,
+        freq_bins: usize,
+        total_windows: usize,
+    },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Cancelled { num_windows, total_windows, .. } => {
+                write!(f, "cancelled after {} of {} windows", num_windows, total_windows)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
+// How many windows pass between progress callbacks: roughly 1% steps, never 0
+pub(crate) fn report_interval(total_windows: usize) -> usize {
+    (total_windows / 100).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::SpectrogramProcessor;
+
+    fn test_signal() -> Vec<f32> {
+        (0..8192).map(|i| (i as f32 * 0.05).sin()).collect()
+    }
+
+    #[test]
+    fn test_progress_reports_every_window_count() {
+        let signal = test_signal();
+        let mut processor = SpectrogramProcessor::new(64);
+        let mut reports = Vec::new();
+
+        let (data, num_windows, freq_bins) = processor
+            .process_windows_with_progress(&signal, 0.5, |done, total| reports.push((done, total)), None)
+            .unwrap();
+
+        assert_eq!((data.clone(), num_windows, freq_bins), processor.process_windows(&signal, 0.5));
+        assert_eq!(reports.last(), Some(&(num_windows, num_windows)));
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_cancel_returns_partial_result() {
+        let signal = test_signal();
+        let mut processor = SpectrogramProcessor::new(64);
+        let token = CancellationToken::new();
+        let worker_token = token.clone();
+
+        let result = processor.process_windows_with_progress(
+            &signal,
+            0.5,
+            |done, _| {
+                if done >= 10 {
+                    token.cancel();
+                }
+            },
+            Some(&worker_token),
+        );
+
+        let (full, _, _) = processor.process_windows(&signal, 0.5);
+        match result {
+            Err(ProcessError::Cancelled { data, num_windows, freq_bins, total_windows }) => {
+                assert!(num_windows >= 10 && num_windows < total_windows);
+                assert_eq!(data.len(), num_windows * freq_bins);
+                assert_eq!(data[..], full[..data.len()]);
+            }
+            Ok(_) => panic!("expected cancellation"),
+        }
+    }
+
+    #[test]
+    fn test_compute_spectrogram_progress_and_cancel() {
+        let signal = test_signal();
+        let mut processor = SpectrogramProcessor::new(64);
+        let mut reports = Vec::new();
+        let full = processor
+            .compute_spectrogram_with_progress(&signal, 0.5, |done, total| reports.push((done, total)), None)
+            .unwrap();
+        assert_eq!(full, processor.compute_spectrogram(&signal, 0.5));
+        assert_eq!(reports.last(), Some(&(255, 255)));
+
+        let token = CancellationToken::new();
+        let result = processor.compute_spectrogram_with_progress(&signal, 0.5, |done, _| if done >= 20 { token.cancel() }, Some(&token));
+        match result {
+            Err(ProcessError::Cancelled { data, num_windows, .. }) => {
+                assert!((20..255).contains(&num_windows));
+                assert_eq!(data[..], full[..num_windows * 32]);
+            }
+            Ok(_) => panic!("expected cancellation"),
+        }
+    }
+
+    #[test]
+    fn test_cancel_before_start() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut processor = SpectrogramProcessor::new(64);
+
+        let err = processor
+            .process_windows_with_progress(&test_signal(), 0.5, |_, _| {}, Some(&token))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "cancelled after 0 of 255 windows");
+    }
+}
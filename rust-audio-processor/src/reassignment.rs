@@ -0,0 +1,264 @@
+// Time-frequency reassignment and synchrosqueezing
+// Every frame is transformed three times: with the Hann window h, its derivative dh and
+// the time-weighted t * h (Auger & Flandrin). For bin k,
+//   instantaneous frequency = k - Im(X_dh X_h*) / |X_h|^2 * N / 2pi   (bins)
+//   group delay             = Re(X_th X_h*) / |X_h|^2                 (samples from centre)
+// The reassigned spectrogram moves each bin's power to those coordinates. The
+// synchrosqueezed one only moves along frequency and sums the complex values, so it stays
+// invertible frame by frame. Bins far below their frame's peak carry no usable phase and
+// stay where they are.
+use crate::audio_processor::{frame_starts, hop_size};
+use crate::fft::{fft_with_cache, TwiddleCache};
+use crate::hann_window::generate_hann_window;
+use crate::utils::Complex;
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReassignmentAnalysis {
+    pub num_windows: usize,
+    pub freq_bins: usize,
+    // `process_windows` magnitudes, num_windows x freq_bins
+    pub magnitudes: Vec<f32>,
+    // Per-bin instantaneous frequency in Hz
+    pub instantaneous_frequency_hz: Vec<f32>,
+    // Per-bin group delay in seconds, relative to the frame centre (positive = later)
+    pub group_delay_s: Vec<f32>,
+}
+
+pub struct ReassignmentProcessor {
+    sample_rate: f32,
+    fft_size: usize,
+    twiddle_cache: TwiddleCache,
+    window: Vec<f32>,
+    derivative_window: Vec<f32>,
+    time_window: Vec<f32>,
+    // Power below the frame peak times this keeps its own coordinates
+    threshold: f32,
+    buffers: [Vec<Complex>; 3],
+}
+
+impl ReassignmentProcessor {
+    pub fn new(sample_rate: f32, fft_size: usize) -> ReassignmentProcessor {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two() && fft_size >= 4, "FFT size must be power of 2");
+        // Same (symmetric) Hann as `SpectrogramProcessor`, so the magnitudes match
+        let window = generate_hann_window(fft_size);
+        let period = fft_size as f32 - 1.0;
+        let centre = period / 2.0;
+        let derivative_window = (0..fft_size)
+            .map(|n| PI / period * (2.0 * PI * n as f32 / period).sin())
+            .collect();
+        let time_window = window.iter().enumerate().map(|(n, &h)| (n as f32 - centre) * h).collect();
+        let buffer = vec![Complex::new(0.0, 0.0); fft_size];
+
+        ReassignmentProcessor {
+            sample_rate,
+            fft_size,
+            twiddle_cache: TwiddleCache::new(fft_size),
+            window,
+            derivative_window,
+            time_window,
+            threshold: 1e-8,
+            buffers: [buffer.clone(), buffer.clone(), buffer],
+        }
+    }
+
+    // Bins more than `db` below their frame's loudest bin are not moved (default -80)
+    pub fn with_threshold_db(mut self, db: f32) -> Self {
+        assert!(db <= 0.0, "threshold must be <= 0 dB");
+        self.threshold = 10f32.powf(db / 10.0);
+        self
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    // Hop between consecutive windows for a given overlap fraction (never 0)
+    pub fn hop_size(&self, overlap: f32) -> usize {
+        hop_size(self.fft_size, overlap)
+    }
+
+    // Magnitudes plus per-bin instantaneous frequency and group delay
+    pub fn analyze(&mut self, audio_data: &[f32], overlap: f32) -> ReassignmentAnalysis {
+        let freq_bins = self.fft_size / 2;
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let mut analysis = ReassignmentAnalysis {
+            num_windows: 0,
+            freq_bins,
+            magnitudes: Vec::new(),
+            instantaneous_frequency_hz: Vec::new(),
+            group_delay_s: Vec::new(),
+        };
+        let sample_rate = self.sample_rate;
+        analysis.num_windows = self.for_each_frame(audio_data, overlap, |_, coordinates, _| {
+            for &(power, frequency, delay) in coordinates {
+                analysis.magnitudes.push(power.sqrt());
+                analysis.instantaneous_frequency_hz.push(frequency * bin_hz);
+                analysis.group_delay_s.push(delay / sample_rate);
+            }
+        });
+        analysis
+    }
+
+    // Power moved to (group delay, instantaneous frequency) and shown as magnitudes on
+    // the `process_windows` grid. Power pushed past either end of the grid is dropped.
+    pub fn reassigned(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, usize, usize) {
+        let freq_bins = self.fft_size / 2;
+        let hop = self.hop_size(overlap) as f32;
+        let total = frame_starts(audio_data.len(), self.fft_size, self.hop_size(overlap)).len();
+        let mut power = vec![0.0f32; total * freq_bins];
+        self.for_each_frame(audio_data, overlap, |window_idx, coordinates, _| {
+            for &(p, frequency, delay) in coordinates {
+                let t = (window_idx as f32 + delay / hop).round();
+                let k = frequency.round();
+                if t >= 0.0 && (t as usize) < total && k >= 0.0 && (k as usize) < freq_bins {
+                    power[t as usize * freq_bins + k as usize] += p;
+                }
+            }
+        });
+        (power.into_iter().map(f32::sqrt).collect(), total, freq_bins)
+    }
+
+    // Complex STFT values summed into their instantaneous-frequency bins, per frame
+    pub fn synchrosqueezed(&mut self, audio_data: &[f32], overlap: f32) -> (Vec<f32>, usize, usize) {
+        let freq_bins = self.fft_size / 2;
+        let mut result = Vec::new();
+        let mut squeezed = vec![Complex::new(0.0, 0.0); freq_bins];
+        let num_windows = self.for_each_frame(audio_data, overlap, |_, coordinates, spectrum| {
+            squeezed.iter_mut().for_each(|c| *c = Complex::new(0.0, 0.0));
+            for (&(_, frequency, _), &value) in coordinates.iter().zip(spectrum) {
+                let k = frequency.round();
+                if k >= 0.0 && (k as usize) < freq_bins {
+                    squeezed[k as usize] = Complex::add(squeezed[k as usize], value);
+                }
+            }
+            result.extend(squeezed.iter().map(|c| c.magnitude()));
+        });
+        (result, num_windows, freq_bins)
+    }
+
+    // Hand every frame's (power, frequency in bins, delay in samples) per bin, plus the
+    // centre-referenced spectrum, to `visit`. Returns the number of frames.
+    fn for_each_frame<F: FnMut(usize, &[(f32, f32, f32)], &[Complex])>(&mut self, audio_data: &[f32], overlap: f32, mut visit: F) -> usize {
+        let freq_bins = self.fft_size / 2;
+        let starts = frame_starts(audio_data.len(), self.fft_size, self.hop_size(overlap));
+        let num_windows = starts.len();
+        let centre = (self.fft_size as f32 - 1.0) / 2.0;
+        let mut coordinates = vec![(0.0, 0.0, 0.0); freq_bins];
+        let mut centred = vec![Complex::new(0.0, 0.0); freq_bins];
+
+        for (window_idx, start) in starts.enumerate() {
+            let frame = &audio_data[start..start + self.fft_size];
+            let windows = [&self.window, &self.derivative_window, &self.time_window];
+            for (buffer, window) in self.buffers.iter_mut().zip(windows) {
+                for ((slot, &sample), &w) in buffer.iter_mut().zip(frame).zip(window.iter()) {
+                    *slot = Complex::new(sample * w, 0.0);
+                }
+                fft_with_cache(buffer, &self.twiddle_cache);
+            }
+
+            let [x_h, x_dh, x_th] = &self.buffers;
+            let peak = x_h[..freq_bins].iter().map(|c| c.real * c.real + c.imag * c.imag).fold(0.0, f32::max);
+            for k in 0..freq_bins {
+                let power = x_h[k].real * x_h[k].real + x_h[k].imag * x_h[k].imag;
+                coordinates[k] = if power > peak * self.threshold && power > 0.0 {
+                    let cross_d = Complex::multiply(x_dh[k], x_h[k].conj());
+                    let cross_t = Complex::multiply(x_th[k], x_h[k].conj());
+                    let frequency = k as f32 - cross_d.imag / power * self.fft_size as f32 / (2.0 * PI);
+                    (power, frequency, cross_t.real / power)
+                } else {
+                    (power, k as f32, 0.0)
+                };
+                // Phase referenced to the window centre, so a tone's main-lobe bins add up
+                let shift = Complex::from_polar(1.0, 2.0 * PI * k as f32 * centre / self.fft_size as f32);
+                centred[k] = Complex::multiply(x_h[k], shift);
+            }
+            visit(window_idx, &coordinates, &centred);
+        }
+
+        num_windows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::sine;
+    use crate::audio_processor::SpectrogramProcessor;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_instantaneous_frequency_of_tone() {
+        // 1234.5 Hz sits between bins of the 1024 FFT (46.875 Hz apart)
+        let signal = sine(1234.5, 0.5, 8192, SAMPLE_RATE);
+        let mut processor = ReassignmentProcessor::new(SAMPLE_RATE, 1024);
+        let analysis = processor.analyze(&signal, 0.5);
+        let (magnitudes, _, _) = SpectrogramProcessor::new(1024).process_windows(&signal, 0.5);
+        assert_eq!(analysis.magnitudes, magnitudes);
+
+        // every main-lobe bin points at the tone, and its group delay is ~0
+        for t in 0..analysis.num_windows {
+            for k in 25..=28 {
+                let i = t * analysis.freq_bins + k;
+                assert!((analysis.instantaneous_frequency_hz[i] - 1234.5).abs() < 0.5, "bin {}: {}", k, analysis.instantaneous_frequency_hz[i]);
+                assert!(analysis.group_delay_s[i].abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_delay_of_click() {
+        let mut signal = vec![0.0f32; 4096];
+        signal[1500] = 1.0;
+        let mut processor = ReassignmentProcessor::new(SAMPLE_RATE, 1024);
+        let analysis = processor.analyze(&signal, 0.75);
+        // frame 3 spans 768..1792 with its centre at 1279.5
+        let frame = &analysis.group_delay_s[3 * 512..4 * 512];
+        for &delay in &frame[10..500] {
+            assert!((delay * SAMPLE_RATE - 220.5).abs() < 0.5, "{}", delay * SAMPLE_RATE);
+        }
+    }
+
+    #[test]
+    fn test_reassigned_concentrates_tone_and_click() {
+        let mut signal = sine(1234.5, 0.5, 16_384, SAMPLE_RATE);
+        signal[8000] += 4.0;
+        let mut processor = ReassignmentProcessor::new(SAMPLE_RATE, 1024);
+        let (plain, _, _) = SpectrogramProcessor::new(1024).process_windows(&signal, 0.75);
+        let (sharp, num_windows, freq_bins) = processor.reassigned(&signal, 0.75);
+        assert_eq!(sharp.len(), plain.len());
+
+        // the tone collapses onto one row: bin 26 (1218.75 Hz)
+        let row_energy = |data: &[f32], k: usize| (0..num_windows).map(|t| data[t * freq_bins + k].powi(2)).sum::<f32>();
+        let tone_rows = |data: &[f32]| row_energy(data, 26) / (20..34).map(|k| row_energy(data, k)).sum::<f32>();
+        assert!(tone_rows(&sharp) > 0.95 && tone_rows(&plain) < 0.7, "{} vs {}", tone_rows(&sharp), tone_rows(&plain));
+
+        // the click collapses onto the frame centred nearest sample 8000
+        let column_energy = |data: &[f32], t: usize| (300..500).map(|k| data[t * freq_bins + k].powi(2)).sum::<f32>();
+        let click_frame = ((8000.0 - 511.5) / 256.0f32).round() as usize;
+        let share = |data: &[f32]| column_energy(data, click_frame) / (0..num_windows).map(|t| column_energy(data, t)).sum::<f32>();
+        assert!(share(&sharp) > 0.95 && share(&plain) < 0.7, "{} vs {}", share(&sharp), share(&plain));
+    }
+
+    #[test]
+    fn test_synchrosqueezed_sharpens_tone() {
+        let signal = sine(1234.5, 0.5, 8192, SAMPLE_RATE);
+        let mut processor = ReassignmentProcessor::new(SAMPLE_RATE, 1024);
+        let (squeezed, num_windows, freq_bins) = processor.synchrosqueezed(&signal, 0.5);
+        let (plain, _, _) = SpectrogramProcessor::new(1024).process_windows(&signal, 0.5);
+        assert_eq!(squeezed.len(), num_windows * freq_bins);
+
+        let t = num_windows / 2;
+        let frame = &squeezed[t * freq_bins..(t + 1) * freq_bins];
+        let loudest = frame.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        assert_eq!(loudest, 26);
+        // the main lobe adds up coherently: well above the plain peak, and nothing
+        // left in the neighbouring bins
+        let plain_peak = plain[t * freq_bins..(t + 1) * freq_bins].iter().cloned().fold(0.0, f32::max);
+        assert!(frame[26] > 1.5 * plain_peak, "{} vs {}", frame[26], plain_peak);
+        assert!(frame[24] < 0.01 * frame[26] && frame[28] < 0.01 * frame[26]);
+    }
+}
@@ -0,0 +1,244 @@
+// Long-term spectrum aggregates: Welch PSD, LTAS, max/min hold and percentile spectra
+// Fed one magnitude frame at a time, so the average panel never needs the spectrogram
+// in memory. Percentiles come from a per-bin level histogram (HISTOGRAM_STEP_DB
+// resolution) and are only tracked when asked for.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::{power_to_db, FLOOR_DB};
+
+const HISTOGRAM_MIN_DB: f32 = -160.0;
+const HISTOGRAM_MAX_DB: f32 = 20.0;
+pub const HISTOGRAM_STEP_DB: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PercentileSpectrum {
+    // 0-1, e.g. 0.5 for the median spectrum
+    pub percentile: f32,
+    pub levels_db: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LongTermSpectrum {
+    pub frames: usize,
+    // Bin k sits at k * bin_hz
+    pub bin_hz: f32,
+    // One-sided Welch estimate in dB re full scale^2 / Hz
+    pub psd_db_per_hz: Vec<f32>,
+    // Mean power per bin in dBFS (a bin-centred full-scale sine reads 0 dB)
+    pub ltas_db: Vec<f32>,
+    pub max_hold_db: Vec<f32>,
+    pub min_hold_db: Vec<f32>,
+    pub percentiles: Vec<PercentileSpectrum>,
+}
+
+pub struct SpectrumAccumulator {
+    sample_rate: f32,
+    fft_size: usize,
+    // Window sum and sum of squares of the processor's window, for the calibrations
+    window_sum: f32,
+    window_power: f32,
+    frames: usize,
+    power_sum: Vec<f64>,
+    max_power: Vec<f32>,
+    min_power: Vec<f32>,
+    percentiles: Vec<f32>,
+    // bins x steps, empty without percentiles
+    histogram: Vec<u32>,
+}
+
+impl SpectrumAccumulator {
+    // Takes the FFT size and window from the processor that produces the frames
+    pub fn new(sample_rate: f32, processor: &SpectrogramProcessor) -> SpectrumAccumulator {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        let fft_size = processor.fft_size();
+        let bins = fft_size / 2;
+        SpectrumAccumulator {
+            sample_rate,
+            fft_size,
+            window_sum: processor.window().iter().sum(),
+            window_power: processor.window().iter().map(|w| w * w).sum(),
+            frames: 0,
+            power_sum: vec![0.0; bins],
+            max_power: vec![0.0; bins],
+            min_power: vec![f32::INFINITY; bins],
+            percentiles: Vec::new(),
+            histogram: Vec::new(),
+        }
+    }
+
+    // Also track these percentiles (0-1) of each bin's level across frames
+    pub fn with_percentiles(mut self, percentiles: &[f32]) -> Self {
+        assert!(percentiles.iter().all(|p| (0.0..=1.0).contains(p)), "percentiles must be in [0, 1]");
+        self.percentiles = percentiles.to_vec();
+        self.histogram = if percentiles.is_empty() {
+            Vec::new()
+        } else {
+            vec![0; self.power_sum.len() * histogram_steps()]
+        };
+        self
+    }
+
+    // One frame of full-resolution `process_window` magnitudes (fft_size / 2 bins)
+    pub fn push(&mut self, magnitudes: &[f32]) {
+        if magnitudes.len() != self.power_sum.len() {
+            return;
+        }
+        self.frames += 1;
+        let steps = histogram_steps();
+        let scale = self.dbfs_scale();
+        for (k, &m) in magnitudes.iter().enumerate() {
+            let power = m * m;
+            self.power_sum[k] += power as f64;
+            self.max_power[k] = self.max_power[k].max(power);
+            self.min_power[k] = self.min_power[k].min(power);
+            if !self.histogram.is_empty() {
+                let db = power_to_db(power * scale);
+                let step = ((db - HISTOGRAM_MIN_DB) / HISTOGRAM_STEP_DB).clamp(0.0, (steps - 1) as f32) as usize;
+                self.histogram[k * steps + step] += 1;
+            }
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    // Snapshot of everything so far; can be taken mid-stream for a live panel
+    pub fn spectrum(&self) -> LongTermSpectrum {
+        let frames = self.frames.max(1) as f64;
+        let scale = self.dbfs_scale();
+        // |X|^2 to one-sided density: 2 / (fs * sum(w^2))
+        let density = 2.0 / (self.sample_rate * self.window_power);
+        let mean_power: Vec<f32> = self.power_sum.iter().map(|&p| (p / frames) as f32).collect();
+        let empty = self.frames == 0;
+        let hold = |values: &[f32]| -> Vec<f32> {
+            values
+                .iter()
+                .map(|&p| if empty { FLOOR_DB } else { power_to_db(p * scale) })
+                .collect()
+        };
+
+        LongTermSpectrum {
+            frames: self.frames,
+            bin_hz: self.sample_rate / self.fft_size as f32,
+            psd_db_per_hz: mean_power.iter().map(|&p| power_to_db(p * density)).collect(),
+            ltas_db: mean_power.iter().map(|&p| power_to_db(p * scale)).collect(),
+            max_hold_db: hold(&self.max_power),
+            min_hold_db: hold(&self.min_power),
+            percentiles: self
+                .percentiles
+                .iter()
+                .map(|&percentile| PercentileSpectrum {
+                    percentile,
+                    levels_db: (0..self.power_sum.len()).map(|k| self.histogram_percentile(k, percentile)).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    // |X|^2 to the power of a sine's amplitude squared: amplitude = 2 |X| / sum(w)
+    fn dbfs_scale(&self) -> f32 {
+        4.0 / (self.window_sum * self.window_sum)
+    }
+
+    // Linear interpolation inside the histogram step that holds the target rank
+    fn histogram_percentile(&self, bin: usize, percentile: f32) -> f32 {
+        if self.frames == 0 {
+            return FLOOR_DB;
+        }
+        let steps = histogram_steps();
+        let counts = &self.histogram[bin * steps..(bin + 1) * steps];
+        let target = percentile * self.frames as f32;
+        let mut cumulative = 0.0;
+        for (step, &count) in counts.iter().enumerate() {
+            let next = cumulative + count as f32;
+            if count > 0 && next >= target {
+                let fraction = ((target - cumulative) / count as f32).clamp(0.0, 1.0);
+                return HISTOGRAM_MIN_DB + (step as f32 + fraction) * HISTOGRAM_STEP_DB;
+            }
+            cumulative = next;
+        }
+        HISTOGRAM_MAX_DB
+    }
+}
+
+fn histogram_steps() -> usize {
+    ((HISTOGRAM_MAX_DB - HISTOGRAM_MIN_DB) / HISTOGRAM_STEP_DB) as usize
+}
+
+impl SpectrogramProcessor {
+    // Aggregates over every window of `process_windows` without storing the frames.
+    // Strides are a display decimation and are ignored, so every frame is averaged.
+    pub fn long_term_spectrum(&mut self, audio_data: &[f32], overlap: f32, sample_rate: f32, percentiles: &[f32]) -> LongTermSpectrum {
+        let mut accumulator = SpectrumAccumulator::new(sample_rate, self).with_percentiles(percentiles);
+        let fft_size = self.fft_size();
+        for start in self.frame_starts(audio_data.len(), overlap) {
+            accumulator.push(&self.process_window(&audio_data[start..start + fft_size]));
+        }
+        accumulator.spectrum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::SpectrogramStream;
+    use crate::test_signals::{brickwall_lowpass, white_noise};
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    #[test]
+    fn test_white_noise_psd_and_holds() {
+        // Uniform +-a noise: variance a^2 / 3 spread over fs / 2 one-sided
+        let amplitude = 0.5;
+        let noise = white_noise(1 << 17, amplitude, 11);
+        let spectrum = SpectrogramProcessor::new(1024).long_term_spectrum(&noise, 0.5, SAMPLE_RATE, &[0.1, 0.5, 0.9]);
+        let expected = 10.0 * (amplitude * amplitude / 3.0 / (SAMPLE_RATE / 2.0)).log10();
+
+        let inner = 4..508;
+        let mean = |values: &[f32]| values[inner.clone()].iter().sum::<f32>() / inner.len() as f32;
+        assert!((mean(&spectrum.psd_db_per_hz) - expected).abs() < 0.3, "{} vs {}", mean(&spectrum.psd_db_per_hz), expected);
+        assert!(mean(&spectrum.max_hold_db) > mean(&spectrum.ltas_db) + 5.0);
+        assert!(mean(&spectrum.min_hold_db) < mean(&spectrum.ltas_db) - 10.0);
+
+        // Exponential per-bin power: median sits ln(2) below the mean (-1.6 dB),
+        // and the percentiles are ordered
+        let p = &spectrum.percentiles;
+        assert!((mean(&p[1].levels_db) - mean(&spectrum.ltas_db) + 1.59).abs() < 0.3);
+        assert!(mean(&p[0].levels_db) < mean(&p[1].levels_db) && mean(&p[1].levels_db) < mean(&p[2].levels_db));
+    }
+
+    #[test]
+    fn test_sine_reads_dbfs() {
+        // Bin-centred -6 dBFS sine
+        let freq = 100.0 * SAMPLE_RATE / 2048.0;
+        let sine: Vec<f32> = (0..1 << 15).map(|i| 0.5 * (2.0 * PI * freq * i as f32 / SAMPLE_RATE).sin()).collect();
+        let spectrum = SpectrogramProcessor::new(2048).long_term_spectrum(&sine, 0.75, SAMPLE_RATE, &[]);
+        assert!((spectrum.ltas_db[100] + 6.02).abs() < 0.1, "{}", spectrum.ltas_db[100]);
+        assert!((spectrum.max_hold_db[100] - spectrum.min_hold_db[100]).abs() < 0.1);
+        assert!(spectrum.percentiles.is_empty());
+    }
+
+    #[test]
+    fn test_stream_matches_buffer() {
+        let signal = brickwall_lowpass(&white_noise(1 << 15, 0.3, 12), 10_000.0, SAMPLE_RATE);
+        let expected = SpectrogramProcessor::new(512).long_term_spectrum(&signal, 0.5, SAMPLE_RATE, &[0.5]);
+
+        let mut stream = SpectrogramStream::new(SpectrogramProcessor::new(512).with_strides(1, 4), 0.5).with_long_term_spectrum(SAMPLE_RATE, &[0.5]);
+        for block in signal.chunks(777) {
+            stream.push(block);
+        }
+        let streamed = stream.long_term_spectrum().unwrap();
+        assert_eq!(streamed.frames, expected.frames);
+        for (a, b) in streamed.ltas_db.iter().zip(expected.ltas_db.iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+        assert_eq!(streamed.percentiles, expected.percentiles);
+        assert_eq!(streamed.max_hold_db, expected.max_hold_db);
+        // The cutoff shows up in the average
+        let bin = |hz: f32| (hz / streamed.bin_hz) as usize;
+        assert!(streamed.ltas_db[bin(5_000.0)] > streamed.ltas_db[bin(15_000.0)] + 40.0);
+    }
+}
@@ -0,0 +1,69 @@
+// Deterministic synthetic signals shared by the analysis tests
+use crate::fft::{fft, ifft};
+use crate::utils::Complex;
+
+// Uniform white noise in [-amplitude, amplitude] from a fixed-seed LCG
+pub fn white_noise(len: usize, amplitude: f32, seed: u64) -> Vec<f32> {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let unit = (state >> 40) as f32 / (1u64 << 24) as f32;
+            (unit * 2.0 - 1.0) * amplitude
+        })
+        .collect()
+}
+
+// Ideal lowpass: zero every FFT bin above `cutoff_hz` (signal length must be a power of 2)
+pub fn brickwall_lowpass(signal: &[f32], cutoff_hz: f32, sample_rate: f32) -> Vec<f32> {
+    let n = signal.len();
+    let mut spectrum: Vec<Complex> = signal.iter().map(|&s| Complex::new(s, 0.0)).collect();
+    fft(&mut spectrum);
+    let cutoff_bin = (cutoff_hz * n as f32 / sample_rate) as usize;
+    for k in cutoff_bin..=n / 2 {
+        spectrum[k] = Complex::new(0.0, 0.0);
+        spectrum[(n - k) % n] = Complex::new(0.0, 0.0);
+    }
+    ifft(&mut spectrum);
+    spectrum.iter().map(|c| c.real).collect()
+}
+
+// Sine of `amplitude` at `freq`, phase in f64 so long tones stay clean
+pub fn sine(freq: f32, amplitude: f32, len: usize, sample_rate: f32) -> Vec<f32> {
+    let step = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate as f64;
+    (0..len).map(|i| amplitude * (step * i as f64).sin() as f32).collect()
+}
+
+pub fn mix(a: &[f32], b: &[f32]) -> Vec<f32> {
+    a.iter().zip(b.iter()).map(|(x, y)| x + y).collect()
+}
+
+// Cut the signal into non-overlapping blocks, expose each block's half spectrum
+// (block / 2 + 1 bins) for editing, and put the block back together. Block length
+// must be a power of 2; a trailing partial block is left untouched.
+pub fn edit_blocks<F: FnMut(usize, &mut [Complex])>(signal: &[f32], block: usize, mut edit: F) -> Vec<f32> {
+    let mut output = signal.to_vec();
+    for (block_idx, chunk) in output.chunks_exact_mut(block).enumerate() {
+        let mut spectrum: Vec<Complex> = chunk.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        fft(&mut spectrum);
+        edit(block_idx, &mut spectrum[..=block / 2]);
+        for k in 1..block / 2 {
+            spectrum[block - k] = spectrum[k].conj();
+        }
+        ifft(&mut spectrum);
+        for (out, c) in chunk.iter_mut().zip(spectrum.iter()) {
+            *out = c.real;
+        }
+    }
+    output
+}
+
+// Fixed-seed coin flips / small integers for the block edits above
+pub fn pseudo_random(index: usize, seed: u64) -> u64 {
+    let mut x = (index as u64).wrapping_add(seed.wrapping_mul(0x9E3779B97F4A7C15));
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^ (x >> 33)
+}
@@ -0,0 +1,339 @@
+// Lossy transcode detection: find the encoder lowpass in the long-term spectrum
+// Lossy encoders cut everything above a bitrate-dependent frequency with a near brickwall
+// filter; natural recordings roll off gently. The detector looks for the steepest large
+// drop in the long-term spectrum and grades how deep and how abrupt it is.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::db::{power_to_db, FLOOR_DB};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TranscodeVerdict {
+    // Content reaches (close to) Nyquist without a brickwall
+    FullBand,
+    // Brickwall lowpass typical of a lossy encoder
+    Lossy { cutoff_hz: f32, likely_source: String },
+    // A drop exists but is too shallow or gentle to call, or there is no signal
+    Inconclusive,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TranscodeAnalysis {
+    pub verdict: TranscodeVerdict,
+    // 0.0 - 1.0
+    pub confidence: f32,
+    // -6 dB point of the strongest drop, if any drop was found
+    pub cutoff_hz: Option<f32>,
+    // Passband level minus stopband level across the drop
+    pub drop_db: f32,
+    // Drop depth over the transition width
+    pub steepness_db_per_khz: f32,
+    // Human readable verdict, e.g. "likely MP3 ~128 kbps (~17.0 kHz lowpass)"
+    pub summary: String,
+}
+
+// Mean power of every bin across frames, in dB (frames are window-major magnitudes)
+pub fn long_term_spectrum_db(frames: &[f32], freq_bins: usize) -> Vec<f32> {
+    let mut power = vec![0.0f64; freq_bins];
+    let mut count = 0usize;
+    for frame in frames.chunks_exact(freq_bins) {
+        for (acc, &m) in power.iter_mut().zip(frame.iter()) {
+            *acc += (m as f64) * (m as f64);
+        }
+        count += 1;
+    }
+    power
+        .iter()
+        .map(|&p| power_to_db((p / count.max(1) as f64) as f32))
+        .collect()
+}
+
+pub(crate) fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return FLOOR_DB;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+// LAME CBR lowpass per bitrate, shared with the codec fingerprint
+pub(crate) const LAME_LOWPASS_HZ: [(u32, f32); 7] = [
+    (64, 11_000.0),
+    (96, 15_100.0),
+    (128, 17_000.0),
+    (160, 17_500.0),
+    (192, 18_600.0),
+    (256, 19_700.0),
+    (320, 20_500.0),
+];
+
+// Bitrate whose LAME lowpass is closest, i.e. split halfway between neighbours
+pub(crate) fn lame_kbps_for_cutoff(cutoff_hz: f32) -> u32 {
+    LAME_LOWPASS_HZ
+        .iter()
+        .min_by(|a, b| (a.1 - cutoff_hz).abs().total_cmp(&(b.1 - cutoff_hz).abs()))
+        .map_or(320, |&(kbps, _)| kbps)
+}
+
+// Other encoders at similar bitrates land on similar lowpasses
+fn likely_source(cutoff_hz: f32) -> &'static str {
+    match lame_kbps_for_cutoff(cutoff_hz) {
+        64 => "MP3 <=64 kbps or low bitrate AAC",
+        96 => "MP3 ~96 kbps",
+        128 => "MP3 ~128 kbps or AAC 128 kbps",
+        160 => "MP3 ~160 kbps",
+        192 => "MP3 ~192 kbps",
+        256 => "MP3 ~256 kbps or V0",
+        _ => "MP3 320 kbps or high bitrate AAC",
+    }
+}
+
+// Light smoothing (5 bins) so single noisy bins do not win an edge search
+pub(crate) fn smooth_db(spectrum_db: &[f32]) -> Vec<f32> {
+    let bins = spectrum_db.len();
+    (0..bins)
+        .map(|k| mean(&spectrum_db[k.saturating_sub(2)..(k + 3).min(bins)]))
+        .collect()
+}
+
+// Strongest downward edge of a smoothed dB spectrum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SpectralEdge {
+    // -6 dB point below the passband level
+    pub cutoff_bin: usize,
+    pub drop_db: f32,
+    pub steepness_db_per_khz: f32,
+}
+
+// Search edges in first..last (first >= span, last <= len - span) for the biggest drop
+// between the `span` bins before and after, then measure depth and transition width
+pub(crate) fn find_edge(smoothed: &[f32], first: usize, last: usize, span: usize, bin_hz: f32) -> SpectralEdge {
+    let bins = smoothed.len();
+    let mut edge = first;
+    let mut best_drop = f32::MIN;
+    for k in first..last {
+        let drop = mean(&smoothed[k - span..k]) - mean(&smoothed[k..k + span]);
+        if drop > best_drop {
+            best_drop = drop;
+            edge = k;
+        }
+    }
+
+    let passband = mean(&smoothed[edge - span..edge]);
+    let stop_end = (edge + 3 * span).min(bins);
+    let stopband = mean(&smoothed[(edge + span).min(stop_end - 1)..stop_end]);
+    let drop_db = (passband - stopband).max(0.0);
+
+    // -6 dB point and transition width (passband -3 dB down to stopband +3 dB)
+    let search_start = edge - span;
+    let cutoff_bin = (search_start..bins)
+        .find(|&k| smoothed[k] < passband - 6.0)
+        .unwrap_or(edge);
+    let transition_start = (search_start..bins)
+        .find(|&k| smoothed[k] < passband - 3.0)
+        .unwrap_or(edge);
+    let transition_end = (transition_start..bins)
+        .find(|&k| smoothed[k] <= stopband + 3.0)
+        .unwrap_or(bins - 1);
+    let width_khz = (transition_end.saturating_sub(transition_start).max(1) as f32 * bin_hz) / 1000.0;
+
+    SpectralEdge {
+        cutoff_bin,
+        drop_db,
+        steepness_db_per_khz: drop_db / width_khz,
+    }
+}
+
+pub struct TranscodeDetector {
+    sample_rate: f32,
+    fft_size: usize,
+    // Drops shallower than this are not treated as a lowpass
+    min_drop_db: f32,
+    // Transitions gentler than this are treated as natural roll-off
+    min_steepness_db_per_khz: f32,
+}
+
+impl TranscodeDetector {
+    pub fn new(sample_rate: f32, fft_size: usize) -> TranscodeDetector {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(fft_size.is_power_of_two(), "FFT size must be power of 2");
+        TranscodeDetector {
+            sample_rate,
+            fft_size,
+            min_drop_db: 20.0,
+            min_steepness_db_per_khz: 40.0,
+        }
+    }
+
+    pub fn with_thresholds(mut self, min_drop_db: f32, min_steepness_db_per_khz: f32) -> Self {
+        self.min_drop_db = min_drop_db;
+        self.min_steepness_db_per_khz = min_steepness_db_per_khz;
+        self
+    }
+
+    // Run the spectrogram and analyse it (mono PCM)
+    pub fn analyze(&self, audio_data: &[f32]) -> TranscodeAnalysis {
+        let mut processor = SpectrogramProcessor::new(self.fft_size);
+        let (frames, _, freq_bins) = processor.process_windows(audio_data, 0.5);
+        self.analyze_spectrogram(&frames, freq_bins)
+    }
+
+    // Analyse existing `process_windows` output (freq_stride 1, same fft_size)
+    pub fn analyze_spectrogram(&self, frames: &[f32], freq_bins: usize) -> TranscodeAnalysis {
+        assert_eq!(freq_bins, self.fft_size / 2, "spectrogram must come from the detector's fft_size");
+        self.analyze_long_term_spectrum(&long_term_spectrum_db(frames, freq_bins))
+    }
+
+    pub fn analyze_long_term_spectrum(&self, spectrum_db: &[f32]) -> TranscodeAnalysis {
+        let bins = spectrum_db.len();
+        let bin_hz = self.sample_rate / self.fft_size as f32;
+        let nyquist = self.sample_rate / 2.0;
+        // Compare ~500 Hz either side of each candidate edge
+        let span = ((500.0 / bin_hz).round() as usize).max(4);
+
+        if bins < 4 * span || spectrum_db.iter().all(|&db| db <= FLOOR_DB) {
+            return inconclusive(None, 0.0, 0.0, "no signal to analyse".to_string());
+        }
+
+        let smoothed = smooth_db(spectrum_db);
+        let first = ((2000.0 / bin_hz) as usize).max(span);
+        // At low sample rates the band above 2 kHz is too short to hold an edge
+        if first + span > bins - span {
+            return inconclusive(None, 0.0, 0.0, "no room above 2 kHz for a lowpass".to_string());
+        }
+        let SpectralEdge {
+            cutoff_bin,
+            drop_db,
+            steepness_db_per_khz: steepness,
+        } = find_edge(&smoothed, first, bins - span, span, bin_hz);
+        let cutoff_hz = cutoff_bin as f32 * bin_hz;
+
+        if drop_db < self.min_drop_db || cutoff_hz >= (0.95 * nyquist).min(21_000.0) {
+            // No real lowpass below the top of the audio band
+            let confidence = (1.0 - drop_db / (2.0 * self.min_drop_db.max(1.0))).clamp(0.3, 0.95);
+            return TranscodeAnalysis {
+                verdict: TranscodeVerdict::FullBand,
+                confidence,
+                cutoff_hz: if drop_db >= self.min_drop_db { Some(cutoff_hz) } else { None },
+                drop_db,
+                steepness_db_per_khz: steepness,
+                summary: "full band".to_string(),
+            };
+        }
+
+        if steepness < self.min_steepness_db_per_khz {
+            return inconclusive(
+                Some(cutoff_hz),
+                drop_db,
+                steepness,
+                format!("gentle roll-off from ~{:.1} kHz, likely natural", cutoff_hz / 1000.0),
+            );
+        }
+
+        let drop_score = ((drop_db - self.min_drop_db) / 30.0).clamp(0.0, 1.0);
+        let steep_score = (steepness / (4.0 * self.min_steepness_db_per_khz)).clamp(0.0, 1.0);
+        let confidence = (0.5 + 0.3 * drop_score + 0.2 * steep_score).min(0.99);
+        let source = likely_source(cutoff_hz);
+
+        TranscodeAnalysis {
+            verdict: TranscodeVerdict::Lossy {
+                cutoff_hz,
+                likely_source: source.to_string(),
+            },
+            confidence,
+            cutoff_hz: Some(cutoff_hz),
+            drop_db,
+            steepness_db_per_khz: steepness,
+            summary: format!("likely {} (~{:.1} kHz lowpass)", source, cutoff_hz / 1000.0),
+        }
+    }
+}
+
+fn inconclusive(cutoff_hz: Option<f32>, drop_db: f32, steepness: f32, summary: String) -> TranscodeAnalysis {
+    TranscodeAnalysis {
+        verdict: TranscodeVerdict::Inconclusive,
+        confidence: 0.0,
+        cutoff_hz,
+        drop_db,
+        steepness_db_per_khz: steepness,
+        summary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{brickwall_lowpass, mix, white_noise};
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    // Lowpassed noise with a faint full-band floor, like a decoded MP3
+    fn transcoded_noise(cutoff_hz: f32) -> Vec<f32> {
+        let music = brickwall_lowpass(&white_noise(1 << 16, 0.5, 7), cutoff_hz, SAMPLE_RATE);
+        mix(&music, &white_noise(1 << 16, 1e-5, 99))
+    }
+
+    #[test]
+    fn test_detects_17k_lowpass() {
+        let analysis = TranscodeDetector::new(SAMPLE_RATE, 4096).analyze(&transcoded_noise(17_000.0));
+
+        match &analysis.verdict {
+            TranscodeVerdict::Lossy { cutoff_hz, likely_source } => {
+                assert!((cutoff_hz - 17_000.0).abs() < 150.0, "cutoff {}", cutoff_hz);
+                assert!(likely_source.contains("128"), "source {}", likely_source);
+            }
+            other => panic!("expected lossy verdict, got {:?}", other),
+        }
+        assert!(analysis.confidence > 0.8);
+        assert!(analysis.drop_db > 60.0);
+        assert!(analysis.summary.starts_with("likely MP3"));
+    }
+
+    #[test]
+    fn test_detects_19k_lowpass() {
+        let analysis = TranscodeDetector::new(SAMPLE_RATE, 4096).analyze(&transcoded_noise(19_500.0));
+        let cutoff = analysis.cutoff_hz.unwrap();
+        assert!(matches!(analysis.verdict, TranscodeVerdict::Lossy { .. }));
+        assert!((cutoff - 19_500.0).abs() < 150.0, "cutoff {}", cutoff);
+    }
+
+    #[test]
+    fn test_full_band_noise() {
+        let analysis = TranscodeDetector::new(SAMPLE_RATE, 4096).analyze(&white_noise(1 << 16, 0.5, 3));
+        assert_eq!(analysis.verdict, TranscodeVerdict::FullBand);
+        assert_eq!(analysis.summary, "full band");
+    }
+
+    #[test]
+    fn test_gentle_rolloff_is_not_lossy() {
+        // One-pole lowpass at ~3 kHz: 6 dB/octave, nothing like a brickwall
+        let noise = white_noise(1 << 16, 0.5, 11);
+        let alpha = 0.35f32;
+        let mut state = 0.0f32;
+        let filtered: Vec<f32> = noise
+            .iter()
+            .map(|&x| {
+                state += alpha * (x - state);
+                state
+            })
+            .collect();
+
+        let analysis = TranscodeDetector::new(SAMPLE_RATE, 4096).analyze(&filtered);
+        assert!(!matches!(analysis.verdict, TranscodeVerdict::Lossy { .. }), "{:?}", analysis);
+    }
+
+    #[test]
+    fn test_silence_is_inconclusive() {
+        let analysis = TranscodeDetector::new(SAMPLE_RATE, 2048).analyze(&vec![0.0; 8192]);
+        assert_eq!(analysis.verdict, TranscodeVerdict::Inconclusive);
+    }
+
+    #[test]
+    fn test_narrow_band_is_inconclusive() {
+        // At 4 kHz the 2 kHz search start sits on Nyquist
+        let noise = white_noise(1 << 14, 0.5, 5);
+        for fft_size in [64, 256, 1024, 4096] {
+            let analysis = TranscodeDetector::new(4000.0, fft_size).analyze(&noise);
+            assert_eq!(analysis.verdict, TranscodeVerdict::Inconclusive, "fft_size {}", fft_size);
+        }
+    }
+}
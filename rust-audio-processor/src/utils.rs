@@ -20,11 +20,14 @@ pub fn bit_reverse(mut x: usize, bits: usize) -> usize {
 
 // Complex Number Operations
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Complex {
     pub real: f32,
     pub imag: f32,
 }
 
+// add/subtract/multiply mirror the original JS helpers rather than std::ops
+#[allow(clippy::should_implement_trait)]
 impl Complex {
     // Consturctor
     pub fn new(real: f32, imag: f32) -> Self {
@@ -59,6 +62,21 @@ impl Complex {
     pub fn magnitude(self) -> f32 {
         (self.real * self.real + self.imag * self.imag).sqrt()
     }
+
+    // Phase angle in radians, (-π, π]
+    pub fn phase(self) -> f32 {
+        self.imag.atan2(self.real)
+    }
+
+    // Complex conjugate
+    pub fn conj(self) -> Complex {
+        Complex::new(self.real, -self.imag)
+    }
+
+    // Rebuild from magnitude and phase
+    pub fn from_polar(magnitude: f32, phase: f32) -> Complex {
+        Complex::new(magnitude * phase.cos(), magnitude * phase.sin())
+    }
 }
 
 // Twiddle Factor Generation
@@ -86,8 +104,27 @@ pub fn butterfly_operation(a: Complex, b: Complex, twiddle: Complex) ->  (Comple
     (upper, lower)
 }
 
+// Pearson correlation, 0.0 when either side is constant
+pub(crate) fn correlation(x: &[f32], y: &[f32]) -> f32 {
+    let n = x.len() as f32;
+    let mean_x = x.iter().sum::<f32>() / n;
+    let mean_y = y.iter().sum::<f32>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0f32, 0.0f32, 0.0f32);
+    for (&a, &b) in x.iter().zip(y.iter()) {
+        let (dx, dy) = (a - mean_x, b - mean_y);
+        cov += dx * dy;
+        var_x += dx * dx;
+        var_y += dy * dy;
+    }
+    if var_x <= 0.0 || var_y <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_x * var_y).sqrt()
+}
+
 // Unit Tests -> Huh So in Rust these are done in files? Neat.
 #[cfg(test)] 
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
 
@@ -197,6 +234,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_polar_roundtrip() {
+        let cases = [
+            Complex::new(1.0, 0.0),
+            Complex::new(0.0, 2.0),
+            Complex::new(-3.0, 4.0),
+            Complex::new(-1.5, -0.5),
+        ];
+
+        for c in cases {
+            let back = Complex::from_polar(c.magnitude(), c.phase());
+            assert!((back.real - c.real).abs() < 1e-5, "real mismatch for {:?}: {:?}", c, back);
+            assert!((back.imag - c.imag).abs() < 1e-5, "imag mismatch for {:?}: {:?}", c, back);
+            assert_eq!(c.conj().imag, -c.imag);
+        }
+    }
+
     #[test]
     fn test_twiddle_factors() {
         // test twiddle factor generation for common FFT sizes
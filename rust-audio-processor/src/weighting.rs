@@ -0,0 +1,188 @@
+// A, C, Z and ITU-R 468 frequency weighting
+// `gain_db` is the exact analogue curve, used for per-bin spectral weights. `filter`
+// is the bilinear-transformed IIR for time-domain use. Bilinear warping pulls the top
+// octave down: at 44.1/48 kHz A and C stay inside IEC 61672 class 1 tolerances, while
+// 468 (which peaks at 6.3 kHz) is only accurate below ~6 kHz unless fs >= 96 kHz.
+use crate::biquad::{polynomial_roots, Cx, Sos};
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Weighting {
+    A,
+    C,
+    // Flat
+    Z,
+    // ITU-R BS.468 (CCIR) noise weighting, 0 dB at 1 kHz
+    Itu468,
+}
+
+// IEC 61672 analogue pole frequencies
+const F1: f64 = 20.598997;
+const F2: f64 = 107.65265;
+const F3: f64 = 737.86223;
+const F4: f64 = 12194.217;
+
+// BS.468 network response |h1 + j h2| in powers of f, as given in the standard's annex
+const ITU_468_DENOMINATOR: [f64; 7] = [
+    1.0,
+    5.559488023498642e-4,
+    1.363894795463638e-7,
+    2.118150887518656e-11,
+    2.043828333606125e-15,
+    1.306612257412824e-19,
+    4.737338981378384e-24,
+];
+const ITU_468_NUMERATOR: f64 = 1.246332637532143e-4;
+
+impl Weighting {
+    // Analogue response in dB
+    pub fn gain_db(&self, freq_hz: f64) -> f64 {
+        if freq_hz <= 0.0 {
+            return match self {
+                Weighting::Z => 0.0,
+                _ => f64::NEG_INFINITY,
+            };
+        }
+        let f2 = freq_hz * freq_hz;
+        match self {
+            Weighting::A => {
+                let r = F4 * F4 * f2 * f2
+                    / ((f2 + F1 * F1) * ((f2 + F2 * F2) * (f2 + F3 * F3)).sqrt() * (f2 + F4 * F4));
+                20.0 * r.log10() + 2.0
+            }
+            Weighting::C => {
+                let r = F4 * F4 * f2 / ((f2 + F1 * F1) * (f2 + F4 * F4));
+                20.0 * r.log10() + 0.062
+            }
+            Weighting::Z => 0.0,
+            Weighting::Itu468 => {
+                // D(u) with u = j f: even powers give h1, odd powers h2
+                let (mut h1, mut h2) = (0.0, 0.0);
+                for (n, &a) in ITU_468_DENOMINATOR.iter().enumerate() {
+                    let term = a * freq_hz.powi(n as i32);
+                    match n % 4 {
+                        0 => h1 += term,
+                        1 => h2 += term,
+                        2 => h1 -= term,
+                        _ => h2 -= term,
+                    }
+                }
+                18.2 + 20.0 * (ITU_468_NUMERATOR * freq_hz / h1.hypot(h2)).log10()
+            }
+        }
+    }
+
+    // Linear amplitude factor for each `process_window` bin (fft_size / 2 of them)
+    pub fn spectral_weights(&self, fft_size: usize, sample_rate: f32) -> Vec<f32> {
+        let bin_hz = sample_rate as f64 / fft_size as f64;
+        (0..fft_size / 2)
+            .map(|k| 10f64.powf(self.gain_db(k as f64 * bin_hz) / 20.0) as f32)
+            .collect()
+    }
+
+    // Weight a `process_window` magnitude frame in place
+    pub fn apply(&self, magnitudes: &mut [f32], sample_rate: f32) {
+        let weights = self.spectral_weights(magnitudes.len() * 2, sample_rate);
+        for (m, w) in magnitudes.iter_mut().zip(weights) {
+            *m *= w;
+        }
+    }
+
+    // IIR for time-domain weighting, matched to the analogue curve at 1 kHz
+    pub fn filter(&self, sample_rate: f64) -> Sos {
+        let (zeros, poles) = match self {
+            Weighting::Z => return Sos::new(Vec::new(), 1.0),
+            Weighting::A => {
+                let mut poles = real_poles(&[F1, F1, F4, F4]);
+                poles.extend(real_poles(&[F2, F3]));
+                (vec![Cx::new(0.0, 0.0); 4], poles)
+            }
+            Weighting::C => (vec![Cx::new(0.0, 0.0); 2], real_poles(&[F1, F1, F4, F4])),
+            Weighting::Itu468 => {
+                // Roots in units of 10 kHz keep the coefficients near 1
+                let scale = 1e4f64;
+                let scaled: Vec<f64> = ITU_468_DENOMINATOR
+                    .iter()
+                    .enumerate()
+                    .map(|(n, a)| a * scale.powi(n as i32))
+                    .collect();
+                // u = j f = s / 2pi
+                let poles = polynomial_roots(&scaled).into_iter().map(|u| u * (2.0 * PI * scale)).collect();
+                (vec![Cx::new(0.0, 0.0)], poles)
+            }
+        };
+        let target = 10f64.powf(self.gain_db(1000.0) / 20.0);
+        Sos::from_analog_zpk(&zeros, &poles, sample_rate).normalized_at(1000.0, sample_rate, target)
+    }
+}
+
+fn real_poles(freqs_hz: &[f64]) -> Vec<Cx> {
+    freqs_hz.iter().map(|f| Cx::new(-2.0 * PI * f, 0.0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analogue_curves_match_tables() {
+        // IEC 61672-1 Table 3 (tabulated at exact base-10 frequencies, so nominal
+        // 31.5 Hz is 10^1.5) and ITU-R BS.468-4 Table 1
+        let cases = [
+            (Weighting::A, 31.623, -39.4),
+            (Weighting::A, 100.0, -19.1),
+            (Weighting::A, 1000.0, 0.0),
+            (Weighting::A, 3981.1, 1.0),
+            (Weighting::A, 10_000.0, -2.5),
+            (Weighting::C, 31.623, -3.0),
+            (Weighting::C, 1000.0, 0.0),
+            (Weighting::C, 10_000.0, -4.4),
+            (Weighting::Itu468, 31.5, -29.9),
+            (Weighting::Itu468, 100.0, -19.8),
+            (Weighting::Itu468, 1000.0, 0.0),
+            (Weighting::Itu468, 2000.0, 5.6),
+            (Weighting::Itu468, 6300.0, 12.2),
+            (Weighting::Itu468, 10_000.0, 8.1),
+            (Weighting::Itu468, 20_000.0, -22.2),
+        ];
+        for (weighting, freq, expected) in cases {
+            let gain = weighting.gain_db(freq);
+            assert!((gain - expected).abs() < 0.1, "{:?} at {} Hz: {}", weighting, freq, gain);
+        }
+        assert_eq!(Weighting::Z.gain_db(50.0), 0.0);
+    }
+
+    #[test]
+    fn test_filters_follow_curves() {
+        for sample_rate in [48_000.0, 96_000.0] {
+            for weighting in [Weighting::A, Weighting::C, Weighting::Itu468, Weighting::Z] {
+                let filter = weighting.filter(sample_rate);
+                // 468 at 48 kHz is only held to the region below its peak
+                let top = if weighting == Weighting::Itu468 && sample_rate < 96_000.0 { 5000.0 } else { 10_000.0 };
+                for freq in [31.5, 100.0, 1000.0, 3150.0, top] {
+                    let digital = 20.0 * filter.magnitude_at(freq, sample_rate).log10();
+                    let analogue = weighting.gain_db(freq);
+                    // Class 1 at 10 kHz allows +2/-3 dB; everything lower is held much tighter
+                    let tolerance = if freq >= 10_000.0 { 2.0 } else { 0.5 };
+                    assert!((digital - analogue).abs() < tolerance, "{:?} {} Hz @ {}: {} vs {}", weighting, freq, sample_rate, digital, analogue);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_spectral_weights() {
+        let weights = Weighting::A.spectral_weights(4096, 48_000.0);
+        assert_eq!(weights.len(), 2048);
+        assert_eq!(weights[0], 0.0);
+        let bin_1k = (1000.0 / (48_000.0 / 4096.0)) as usize;
+        assert!((20.0 * weights[bin_1k].log10()).abs() < 0.1);
+
+        let mut magnitudes = vec![1.0f32; 2048];
+        Weighting::Z.apply(&mut magnitudes, 48_000.0);
+        assert!(magnitudes.iter().all(|&m| m == 1.0));
+        Weighting::C.apply(&mut magnitudes, 48_000.0);
+        assert!(magnitudes[1] < 0.5 && (magnitudes[bin_1k] - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,83 @@
+// Analysis windows for measurement work
+// The spectrogram keeps its symmetric Hann (hann_window.rs); these are periodic (DFT-even)
+// so a tone that lands exactly on a bin stays inside the main lobe.
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowKind {
+    Hann,
+    // 4-term Blackman-Harris: -92 dB sidelobes, main lobe +-4 bins
+    BlackmanHarris,
+    // 5-term flat top: amplitude error under 0.01 dB anywhere in a bin, main lobe +-5 bins
+    FlatTop,
+}
+
+impl WindowKind {
+    fn coefficients(&self) -> &'static [f64] {
+        match self {
+            WindowKind::Hann => &[0.5, 0.5],
+            WindowKind::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            WindowKind::FlatTop => &[0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368],
+        }
+    }
+
+    // Half width of the main lobe in bins, i.e. how far a tone's energy spreads
+    pub fn main_lobe_half_width(&self) -> usize {
+        match self {
+            WindowKind::Hann => 2,
+            WindowKind::BlackmanHarris => 4,
+            WindowKind::FlatTop => 5,
+        }
+    }
+}
+
+// Sum of cosines with alternating signs, periodic in `size`
+pub fn generate_window(kind: WindowKind, size: usize) -> Vec<f32> {
+    let coefficients = kind.coefficients();
+    (0..size)
+        .map(|i| {
+            let phase = 2.0 * PI * i as f64 / size as f64;
+            coefficients
+                .iter()
+                .enumerate()
+                .map(|(k, &a)| if k % 2 == 0 { a } else { -a } * (k as f64 * phase).cos())
+                .sum::<f64>() as f32
+        })
+        .collect()
+}
+
+// Mean of the window: the amplitude a bin-centred tone reads relative to no window
+pub fn coherent_gain(window: &[f32]) -> f32 {
+    window.iter().sum::<f32>() / window.len() as f32
+}
+
+// Equivalent noise bandwidth in bins: N * sum(w^2) / sum(w)^2
+pub fn equivalent_noise_bandwidth(window: &[f32]) -> f32 {
+    let sum: f32 = window.iter().sum();
+    let sum_squares: f32 = window.iter().map(|w| w * w).sum();
+    window.len() as f32 * sum_squares / (sum * sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_figures() {
+        // Textbook coherent gain and ENBW for each window
+        let cases = [
+            (WindowKind::Hann, 0.5, 1.5),
+            (WindowKind::BlackmanHarris, 0.35875, 2.0044),
+            (WindowKind::FlatTop, 0.2156, 3.77),
+        ];
+        for (kind, gain, enbw) in cases {
+            let window = generate_window(kind, 4096);
+            assert!((coherent_gain(&window) - gain).abs() < 1e-3, "{:?}", kind);
+            assert!((equivalent_noise_bandwidth(&window) - enbw).abs() < 0.01, "{:?} {}", kind, equivalent_noise_bandwidth(&window));
+            // periodic: starts at the minimum, peaks in the middle
+            assert!(window[0] < 1e-3);
+            assert!(window[2048] > window[1024]);
+        }
+    }
+}
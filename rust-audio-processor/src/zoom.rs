@@ -0,0 +1,186 @@
+// Zoom FFT: the spectrum of one band at fine bin spacing, via the chirp-z transform
+// Bluestein's identity nm = (n^2 + m^2 - (m - n)^2) / 2 turns the band's DTFT samples
+// into a convolution with a chirp, done with one power-of-2 FFT and one inverse. The
+// chirp's spectrum and the pre/post chirps are planned once per (frame size, band,
+// bins). Only the window length sets the frequency resolution; the zoom sets how
+// finely that is sampled and keeps the output to the band.
+use crate::audio_processor::SpectrogramProcessor;
+use crate::fft::{fft_with_cache, ifft_with_cache, TwiddleCache};
+use crate::utils::Complex;
+use std::f64::consts::PI;
+
+pub struct ZoomFft {
+    frame_size: usize,
+    bin_hz: Vec<f32>,
+    // A^-n W^(n^2 / 2)
+    pre_chirp: Vec<Complex>,
+    // FFT of W^(-k^2 / 2), wrapped for circular convolution
+    chirp_spectrum: Vec<Complex>,
+    // W^(m^2 / 2)
+    post_chirp: Vec<Complex>,
+    twiddle_cache: TwiddleCache,
+}
+
+impl ZoomFft {
+    // `num_bins` points from min_hz to max_hz inclusive, for frames of `frame_size`
+    // samples (any length)
+    pub fn new(sample_rate: f32, frame_size: usize, min_hz: f32, max_hz: f32, num_bins: usize) -> ZoomFft {
+        assert!(sample_rate > 0.0, "sample_rate must be positive");
+        assert!(frame_size >= 1, "frame_size must be >= 1");
+        assert!(num_bins >= 2, "num_bins must be >= 2");
+        assert!(min_hz >= 0.0 && max_hz > min_hz && max_hz <= sample_rate / 2.0, "need 0 <= min_hz < max_hz <= Nyquist");
+
+        let fs = sample_rate as f64;
+        let start = min_hz as f64 / fs;
+        let step = (max_hz - min_hz) as f64 / ((num_bins - 1) as f64 * fs);
+        // Phases in cycles, reduced before the multiply by 2pi so n^2 stays exact
+        let chirp = |k: usize, sign: f64| {
+            let cycles = (step * (k * k) as f64 / 2.0).rem_euclid(1.0);
+            Complex::from_polar(1.0, (sign * 2.0 * PI * cycles) as f32)
+        };
+
+        let pre_chirp = (0..frame_size)
+            .map(|n| {
+                let shift = Complex::from_polar(1.0, (-2.0 * PI * (start * n as f64).rem_euclid(1.0)) as f32);
+                Complex::multiply(shift, chirp(n, -1.0))
+            })
+            .collect();
+        let post_chirp = (0..num_bins).map(|m| chirp(m, -1.0)).collect();
+
+        let conv_size = (frame_size + num_bins - 1).next_power_of_two();
+        let twiddle_cache = TwiddleCache::new(conv_size);
+        let mut chirp_spectrum = vec![Complex::new(0.0, 0.0); conv_size];
+        for (k, slot) in chirp_spectrum.iter_mut().enumerate().take(num_bins) {
+            *slot = chirp(k, 1.0);
+        }
+        for k in 1..frame_size {
+            chirp_spectrum[conv_size - k] = chirp(k, 1.0);
+        }
+        fft_with_cache(&mut chirp_spectrum, &twiddle_cache);
+
+        ZoomFft {
+            frame_size,
+            bin_hz: (0..num_bins).map(|m| min_hz + (m as f64 * step * fs) as f32).collect(),
+            pre_chirp,
+            chirp_spectrum,
+            post_chirp,
+            twiddle_cache,
+        }
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.bin_hz.len()
+    }
+
+    pub fn bin_frequencies(&self) -> &[f32] {
+        &self.bin_hz
+    }
+
+    // DTFT of an already-windowed frame at every zoom bin, scaled like the FFT, so a
+    // frequency that is also an FFT bin gives exactly the `process_window` value
+    pub fn transform(&self, frame: &[f32]) -> Vec<Complex> {
+        assert_eq!(frame.len(), self.frame_size, "frame length must match the zoom plan");
+        let mut buffer = vec![Complex::new(0.0, 0.0); self.chirp_spectrum.len()];
+        for ((slot, &sample), &pre) in buffer.iter_mut().zip(frame).zip(&self.pre_chirp) {
+            *slot = Complex::new(sample * pre.real, sample * pre.imag);
+        }
+        fft_with_cache(&mut buffer, &self.twiddle_cache);
+        for (slot, &chirp) in buffer.iter_mut().zip(&self.chirp_spectrum) {
+            *slot = Complex::multiply(*slot, chirp);
+        }
+        ifft_with_cache(&mut buffer, &self.twiddle_cache);
+        buffer.iter().zip(&self.post_chirp).map(|(&value, &post)| Complex::multiply(value, post)).collect()
+    }
+
+    pub fn magnitudes(&self, frame: &[f32]) -> Vec<f32> {
+        self.transform(frame).iter().map(|c| c.magnitude()).collect()
+    }
+}
+
+impl SpectrogramProcessor {
+    // `process_windows` restricted to the zoom band: same frames, window and time stride,
+    // num_bins columns instead of fft_size / 2. The zoom must be planned for fft_size.
+    pub fn process_windows_zoom(&mut self, audio_data: &[f32], overlap: f32, zoom: &ZoomFft) -> (Vec<f32>, usize, usize) {
+        let fft_size = self.fft_size();
+        assert_eq!(zoom.frame_size(), fft_size, "zoom must be planned for the processor's fft_size");
+        let starts = self.window_starts(audio_data.len(), overlap);
+        let num_windows = starts.len();
+        let mut data = Vec::with_capacity(num_windows * zoom.num_bins());
+        let mut windowed = vec![0.0f32; fft_size];
+        for start in starts {
+            let frame = &audio_data[start..start + fft_size];
+            for ((slot, &sample), &w) in windowed.iter_mut().zip(frame).zip(self.window()) {
+                *slot = sample * w;
+            }
+            data.extend(zoom.magnitudes(&windowed));
+        }
+        (data, num_windows, zoom.num_bins())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fft::fft;
+    use crate::test_signals::{sine, white_noise};
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    #[test]
+    fn test_full_band_matches_fft() {
+        // zooming on 0..Nyquist with N/2 + 1 points is just the FFT
+        let signal = white_noise(1024, 0.5, 50);
+        let zoom = ZoomFft::new(SAMPLE_RATE, 1024, 0.0, SAMPLE_RATE / 2.0, 513);
+        let mut spectrum: Vec<Complex> = signal.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        fft(&mut spectrum);
+        for (z, f) in zoom.transform(&signal).iter().zip(&spectrum[..=512]) {
+            assert!((z.real - f.real).abs() < 1e-3 && (z.imag - f.imag).abs() < 1e-3, "{:?} vs {:?}", z, f);
+        }
+    }
+
+    #[test]
+    fn test_band_points_match_process_window() {
+        // 15-22 kHz on the 4096 grid (10.77 Hz) at a quarter of the spacing
+        let signal = white_noise(4096, 0.5, 51);
+        let bin_hz = SAMPLE_RATE / 4096.0;
+        let (first, last) = (1393, 2043);
+        let zoom = ZoomFft::new(SAMPLE_RATE, 4096, first as f32 * bin_hz, last as f32 * bin_hz, (last - first) * 4 + 1);
+        let mut processor = SpectrogramProcessor::new(4096);
+        let plain = processor.process_window(&signal);
+        let (zoomed, num_windows, num_bins) = processor.process_windows_zoom(&signal, 0.5, &zoom);
+        assert_eq!((num_windows, num_bins), (1, (last - first) * 4 + 1));
+        // every fourth zoom bin lands on an FFT bin
+        for k in first..=last {
+            let z = zoomed[(k - first) * 4];
+            assert!((z - plain[k]).abs() < 1e-3 * plain[k].max(1.0), "bin {}: {} vs {}", k, z, plain[k]);
+        }
+    }
+
+    #[test]
+    fn test_zoom_locates_tone_between_bins() {
+        let signal = sine(19_005.3, 0.5, 8192, SAMPLE_RATE);
+        let zoom = ZoomFft::new(SAMPLE_RATE, 8192, 18_900.0, 19_100.0, 201);
+        let mut processor = SpectrogramProcessor::new(8192);
+        let (zoomed, _, num_bins) = processor.process_windows_zoom(&signal, 0.0, &zoom);
+        let loudest = zoomed.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        // 1 Hz zoom bins against 5.4 Hz FFT bins
+        assert_eq!(num_bins, 201);
+        assert!((zoom.bin_frequencies()[loudest] - 19_005.3).abs() <= 0.5, "{}", zoom.bin_frequencies()[loudest]);
+        // the peak is the full Hann amplitude A * N / 4, with no scalloping loss
+        assert!((zoomed[loudest] / (0.5 * 8192.0 / 4.0) - 1.0).abs() < 0.01, "{}", zoomed[loudest]);
+    }
+
+    #[test]
+    fn test_time_stride_applies() {
+        let signal = white_noise(20_000, 0.5, 52);
+        let zoom = ZoomFft::new(SAMPLE_RATE, 1024, 15_000.0, 22_000.0, 300);
+        let (all, full_windows, _) = SpectrogramProcessor::new(1024).process_windows_zoom(&signal, 0.5, &zoom);
+        let (strided, num_windows, _) = SpectrogramProcessor::new(1024).with_strides(3, 1).process_windows_zoom(&signal, 0.5, &zoom);
+        assert_eq!(num_windows, full_windows.div_ceil(3));
+        assert_eq!(&strided[300..600], &all[3 * 300..4 * 300]);
+    }
+}